@@ -1,28 +1,212 @@
-const grpc = require('grpc')
-const Try = grpc.load('./try.proto')
+const grpc = require('@grpc/grpc-js')
+const protoLoader = require('@grpc/proto-loader')
+const { GraphQLScalarType, Kind } = require('graphql')
 
-const PizzeriaStub = new Try.Pizzeria(process.env.PIZZERIA_BACKEND_URL, grpc.credentials.createInsecure())
+const wellKnownTypes = {
+  fromTimestamp: timestamp => {
+    if (timestamp == null) return null
+    const nanos = timestamp.nanos || 0
+    const date = new Date(Number(timestamp.seconds || 0) * 1000).toISOString().replace(/\.\d+Z$/, '')
+    return nanos ? `${date}.${String(nanos).padStart(9, '0').replace(/0+$/, '')}Z` : `${date}Z`
+  },
+  toTimestamp: dateTime => {
+    if (dateTime == null) return null
+    const [, date, fraction = '', offset] = /^(.+?)(?:\.(\d{1,9}))?(Z|[+-]\d\d:\d\d)$/i.exec(dateTime)
+    return {
+      seconds: String(Math.floor(Date.parse(date + offset) / 1000)),
+      nanos: Number(fraction.padEnd(9, '0')),
+    }
+  },
+  fromDuration: duration => {
+    if (duration == null) return null
+    const seconds = BigInt(String(duration.seconds || 0))
+    const nanos = duration.nanos || 0
+    const sign = seconds < 0 || nanos < 0 ? '-' : ''
+    const fraction = nanos ? `.${String(Math.abs(nanos)).padStart(9, '0').replace(/0+$/, '')}` : ''
+    return `${sign}${seconds < 0 ? -seconds : seconds}${fraction}s`
+  },
+  toDuration: duration => {
+    if (duration == null) return null
+    const [, sign, seconds, fraction = ''] = /^(-?)(\d+)(?:\.(\d{1,9}))?s$/.exec(duration)
+    const nanos = Number(fraction.padEnd(9, '0'))
+    return { seconds: sign + seconds, nanos: sign ? -nanos : nanos }
+  },
+  fromEmpty: () => true,
+  toEmpty: () => ({}),
+  fromFieldMask: fieldMask => fieldMask == null ? null : fieldMask.paths || [],
+  toFieldMask: paths => paths == null ? null : { paths },
+  fromStruct: struct => {
+    if (struct == null) return null
+    const object = {}
+    Object.keys(struct.fields || {}).forEach(key => {
+      object[key] = wellKnownTypes.fromValue(struct.fields[key])
+    })
+    return object
+  },
+  toStruct: object => {
+    if (object == null) return null
+    const fields = {}
+    Object.keys(object).forEach(key => {
+      fields[key] = wellKnownTypes.toValue(object[key])
+    })
+    return { fields }
+  },
+  fromValue: value => {
+    if (value == null) return null
+    switch (value.kind || Object.keys(value).find(key => value[key] != null)) {
+      case 'numberValue': return value.numberValue
+      case 'stringValue': return value.stringValue
+      case 'boolValue': return value.boolValue
+      case 'structValue': return wellKnownTypes.fromStruct(value.structValue)
+      case 'listValue': return wellKnownTypes.fromListValue(value.listValue)
+      default: return null
+    }
+  },
+  toValue: json => {
+    if (json === undefined) return undefined
+    if (json === null) return { nullValue: 'NULL_VALUE' }
+    if (Array.isArray(json)) return { listValue: wellKnownTypes.toListValue(json) }
+    switch (typeof json) {
+      case 'number': return { numberValue: json }
+      case 'string': return { stringValue: json }
+      case 'boolean': return { boolValue: json }
+      default: return { structValue: wellKnownTypes.toStruct(json) }
+    }
+  },
+  fromListValue: listValue => listValue == null ? null : (listValue.values || []).map(wellKnownTypes.fromValue),
+  toListValue: values => values == null ? null : { values: values.map(wellKnownTypes.toValue) },
+  fromAny: any => any == null ? null : {
+    '@type': any.type_url,
+    value: Buffer.from(any.value || []).toString('base64'),
+  },
+  toAny: json => json == null ? null : {
+    type_url: json['@type'],
+    value: Buffer.from(json.value || '', 'base64'),
+  },
+  fromWrapper: wrapper => wrapper == null ? null : wrapper.value,
+  toWrapper: value => value == null ? null : { value },
+}
+
+const subscribeTo = (call, convert) => {
+  const responses = []
+  const waiting = []
+  let finished = false
+  const push = response => {
+    if (waiting.length > 0) waiting.shift()({ value: response, done: false })
+    else responses.push(response)
+  }
+  const finish = () => {
+    finished = true
+    while (waiting.length > 0) waiting.shift()({ value: undefined, done: true })
+  }
+  call.on('data', data => {
+    if (!finished) push(convert(data))
+  })
+  call.on('error', err => {
+    if (finished) return
+    push(err)
+    finish()
+  })
+  call.on('end', finish)
+  const stop = () => {
+    if (!finished) {
+      responses.length = 0
+      finish()
+      call.cancel()
+    }
+    return Promise.resolve({ value: undefined, done: true })
+  }
+  return {
+    next: () => {
+      if (responses.length > 0) return Promise.resolve({ value: responses.shift(), done: false })
+      if (finished) return Promise.resolve({ value: undefined, done: true })
+      return new Promise(resolve => waiting.push(resolve))
+    },
+    return: stop,
+    throw: err => stop().then(() => Promise.reject(err)),
+    [Symbol.asyncIterator]() {
+      return this
+    },
+  }
+}
+
+const resolveResponse = response => {
+  if (response instanceof Error) throw response
+  return response
+}
+
+const proto = grpc.loadPackageDefinition(protoLoader.loadSync([
+  'try.proto',
+], {
+  keepCase: true,
+  longs: String,
+  enums: String,
+  defaults: false,
+  oneofs: true,
+}))
+
+const PizzeriaStub = new proto.Pizzeria(process.env.PIZZERIA_BACKEND_URL, grpc.credentials.createInsecure())
+
+const parseDateTimeValue = value => {
+  if (typeof value !== 'string' || !/^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d(\.\d{1,9})?(Z|[+-]\d\d:\d\d)$/i.test(value)) {
+    throw new TypeError(`DateTime cannot represent ${JSON.stringify(value)}`)
+  }
+  return value
+}
+
+const fromGrpc = {
+  Pizza: message => message == null ? null : ({
+    title: message.title ?? '',
+    origin: message.origin ?? '',
+    bakedAt: wellKnownTypes.fromTimestamp(message.baked_at),
+    toppings: (message.toppings || []).map(fromGrpc.Topping),
+    sauce: message.sauce ?? 'TOMATO',
+  }),
+  Topping: message => message == null ? null : ({
+    name: message.name ?? '',
+    sweet: message.sweet ?? false,
+    vitamins: message.vitamins || [],
+  }),
+}
+
+const toGrpc = {
+  Pizza: input => input == null ? null : ({
+    title: input.title,
+    origin: input.origin,
+    baked_at: wellKnownTypes.toTimestamp(input.bakedAt),
+    toppings: input.toppings && input.toppings.map(toGrpc.Topping),
+    sauce: input.sauce,
+  }),
+  Topping: input => input == null ? null : ({
+    name: input.name,
+    sweet: input.sweet,
+    vitamins: input.vitamins,
+  }),
+}
 
 module.exports = {
   Query: {
+  },
+  Mutation: {
     pizzeria: () => ({
       makeSimplePizza: ({ topping: req }) => {
-        return new Promise((resolve, reject) => PizzeriaStub.MakeSimplePizza({...req}, (err, res) => err ? reject(err) : resolve(res)))
+        return new Promise((resolve, reject) => PizzeriaStub.MakeSimplePizza(toGrpc.Topping(req), (err, res) => err ? reject(err) : resolve(fromGrpc.Pizza(res))))
       },
     }),
   },
   Subscription: {
-    pizzeria: () => ({
-        observePizzas: (parent, { topping: req }, { pubsub }) => {
-          const call = PizzeriaStub.ObservePizzas({...req})
-          // taken from the graphql-yoga example
-          // https://github.com/graphcool/graphql-yoga/blob/master/examples/subscriptions/index.jss
-          const channel = Math.random().toString(36).substring(2, 15) // random channel name
-          call.on('data', data => pubsub.publish(channel, data))
-          call.on('end', () => true)
-          call.on('status', () => true)
-          return pubsub.asyncIterator(channel)
-        },
-    }),
+    pizzeriaObservePizzas: {
+      subscribe: (parent, { topping: req }) => {
+        const call = PizzeriaStub.ObservePizzas(toGrpc.Topping(req))
+        return subscribeTo(call, data => fromGrpc.Pizza(data))
+      },
+      resolve: resolveResponse,
+    },
   },
+  DateTime: new GraphQLScalarType({
+    name: 'DateTime',
+    serialize: value => value,
+    parseValue: parseDateTimeValue,
+    parseLiteral: ast => ast.kind === Kind.STRING ? parseDateTimeValue(ast.value) : undefined,
+  }),
 }
\ No newline at end of file
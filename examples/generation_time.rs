@@ -0,0 +1,144 @@
+//! Times the generation over synthetic descriptor sets of growing sizes, with source info like
+//! protoc's `--include_source_info`, to check that it stays linear in the size of the input.
+//!
+//! Run it in release mode, optionally with the number of messages per file of the smallest set:
+//!
+//! ```text
+//! cargo run --release --example generation_time -- 200
+//! ```
+//!
+//! It fails when the time per message of the largest set is over `MAX_SLOWDOWN` times the one of
+//! the smallest.
+
+extern crate protobuf;
+extern crate protoc_gen_apollo;
+
+use protobuf::descriptor::*;
+use protobuf::RepeatedField;
+use protoc_gen_apollo::config::Config;
+use protoc_gen_apollo::gen;
+use std::time::{Duration, Instant};
+
+const FILES: usize = 10;
+const FIELDS: usize = 12;
+const SCALES: [usize; 4] = [1, 2, 4, 8];
+const MAX_SLOWDOWN: f64 = 3.0;
+
+fn commented(path: Vec<i32>, line: usize) -> SourceCodeInfo_Location {
+    let mut location = SourceCodeInfo_Location::new();
+    location.set_path(path);
+    location.set_span(vec![line as i32, 2, 40]);
+    location.set_leading_comments(format!(" The element on line {}.\n", line));
+    location
+}
+
+/// A file with `messages` messages of `FIELDS` fields each, every message referencing the
+/// previous one and an enum, and a service with a method per message.
+fn file(idx: usize, messages: usize) -> FileDescriptorProto {
+    let mut file = FileDescriptorProto::new();
+    file.set_name(format!("file{}.proto", idx));
+    file.set_package(format!("bench.file{}", idx));
+    file.set_syntax("proto3".to_string());
+    let mut locations = Vec::new();
+
+    let mut status = EnumDescriptorProto::new();
+    status.set_name("Status".to_string());
+    for (number, name) in ["STATUS_UNSPECIFIED", "STATUS_OK"].iter().enumerate() {
+        let mut value = EnumValueDescriptorProto::new();
+        value.set_name(name.to_string());
+        value.set_number(number as i32);
+        locations.push(commented(vec![5, 0, 2, number as i32], locations.len()));
+        status.mut_value().push(value);
+    }
+    file.mut_enum_type().push(status);
+
+    let mut service = ServiceDescriptorProto::new();
+    service.set_name(format!("Service{}", idx));
+    for message_idx in 0..messages {
+        let mut message = DescriptorProto::new();
+        message.set_name(format!("Message{}", message_idx));
+        locations.push(commented(vec![4, message_idx as i32], locations.len()));
+        for field_idx in 0..FIELDS {
+            let mut field = FieldDescriptorProto::new();
+            field.set_name(format!("field_{}", field_idx));
+            // Sparse numbers, as found in evolving schemas.
+            field.set_number(2 * field_idx as i32 + 1);
+            field.set_label(FieldDescriptorProto_Label::LABEL_OPTIONAL);
+            match field_idx {
+                0 if message_idx > 0 => {
+                    field.set_field_type(FieldDescriptorProto_Type::TYPE_MESSAGE);
+                    field.set_type_name(format!(".bench.file{}.Message{}", idx, message_idx - 1));
+                }
+                1 => {
+                    field.set_field_type(FieldDescriptorProto_Type::TYPE_ENUM);
+                    field.set_type_name(format!(".bench.file{}.Status", idx));
+                }
+                _ => field.set_field_type(FieldDescriptorProto_Type::TYPE_STRING),
+            }
+            locations.push(commented(
+                vec![4, message_idx as i32, 2, field_idx as i32],
+                locations.len(),
+            ));
+            message.mut_field().push(field);
+        }
+        file.mut_message_type().push(message);
+
+        let mut method = MethodDescriptorProto::new();
+        method.set_name(format!("GetMessage{}", message_idx));
+        method.set_input_type(format!(".bench.file{}.Message{}", idx, message_idx));
+        method.set_output_type(format!(".bench.file{}.Message{}", idx, message_idx));
+        locations.push(commented(
+            vec![6, 0, 2, message_idx as i32],
+            locations.len(),
+        ));
+        service.mut_method().push(method);
+    }
+    file.mut_service().push(service);
+
+    file.mut_source_code_info()
+        .set_location(RepeatedField::from_vec(locations));
+    file
+}
+
+fn time(messages: usize, config: &Config) -> Duration {
+    let files: Vec<FileDescriptorProto> = (0..FILES).map(|idx| file(idx, messages)).collect();
+    let names: Vec<String> = files.iter().map(|f| f.get_name().to_string()).collect();
+
+    let start = Instant::now();
+    gen(&files, &names, config).expect("generation failed");
+    start.elapsed()
+}
+
+fn main() {
+    let base: usize = std::env::args()
+        .nth(1)
+        .map(|arg| arg.parse().expect("the number of messages is not a number"))
+        .unwrap_or(100);
+    let config = Config::from_parameter("").unwrap();
+
+    // Warms up the allocator and the caches.
+    time(base, &config);
+
+    let mut per_message = Vec::new();
+    for scale in SCALES.iter() {
+        let messages = base * scale;
+        let elapsed = time(messages, &config);
+        let micros = elapsed.as_secs_f64() * 1e6 / (messages * FILES) as f64;
+        println!(
+            "{:>7} messages: {:>9.2?} ({:.2}µs per message)",
+            messages * FILES,
+            elapsed,
+            micros
+        );
+        per_message.push(micros);
+    }
+
+    let slowdown = per_message[per_message.len() - 1] / per_message[0];
+    if slowdown > MAX_SLOWDOWN {
+        eprintln!(
+            "the time per message grew {:.1} times, generation is not linear",
+            slowdown
+        );
+        std::process::exit(1);
+    }
+}
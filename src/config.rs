@@ -0,0 +1,669 @@
+use gql::BUILTIN_SCALARS;
+use protobuf::descriptor::FieldDescriptorProto_Type;
+
+/// Which files the plugin should emit for each target file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outputs {
+    /// The raw GraphQL schema (`<file>.out`)
+    pub schema: bool,
+    /// The JS module exporting the type definitions (`<file>-type-defs.js`)
+    pub type_defs: bool,
+    /// The JS module exporting the resolvers (`<file>-resolvers.js`)
+    pub resolvers: bool,
+}
+
+impl Default for Outputs {
+    fn default() -> Outputs {
+        Outputs {
+            schema: true,
+            type_defs: true,
+            resolvers: true,
+        }
+    }
+}
+
+/// Which comments of the proto sources become GraphQL descriptions, each as a paragraph of its
+/// own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comments {
+    /// The comments right before an element
+    pub leading: bool,
+    /// The comments right after an element, on the same line or the next one
+    pub trailing: bool,
+    /// The comments before an element that are separated from it by a blank line
+    pub detached: bool,
+}
+
+impl Default for Comments {
+    fn default() -> Comments {
+        Comments {
+            leading: true,
+            trailing: true,
+            detached: false,
+        }
+    }
+}
+
+/// How the outputs are split between the files protoc asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Emit {
+    /// One set of outputs per file to generate, with the types declared in that file. Types
+    /// imported from other files are only referenced.
+    #[default]
+    PerFile,
+    /// A single set of outputs (`schema.out`, `schema-type-defs.js`, `schema-resolvers.js`) for
+    /// all the files to generate, with the imported types they reference.
+    Merged,
+}
+
+/// How proto field names are rendered as GraphQL field names.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FieldCase {
+    /// The `json_name` of the field, which protoc derives like `baked_at` → `bakedAt` unless it
+    /// is set with the `json_name` field option
+    #[default]
+    Json,
+    /// Use the name from the proto file verbatim
+    Preserve,
+    /// `baked_at` → `bakedAt`
+    Camel,
+    /// `bakedAt` → `baked_at`
+    Snake,
+}
+
+/// How the GraphQL names of messages and enums are derived from their fully qualified proto
+/// names. Nested types are always prefixed with the names of the messages they are nested in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TypeNaming {
+    /// Prefixed with the whole package: `acme.pizza.v1.Pizza` → `AcmePizzaV1Pizza`
+    #[default]
+    Package,
+    /// Prefixed with the package, without the part the packages of the generated files share:
+    /// `acme.pizza.v1.Pizza` → `V1Pizza` next to `acme.pizza.v2`
+    StripCommonPrefix,
+    /// Not prefixed: `acme.pizza.v1.Pizza` → `Pizza`
+    Short,
+}
+
+/// Where the fields of the methods are in the `Query` and `Mutation` root types. Subscriptions
+/// are always root fields.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RootFields {
+    /// Grouped by service: `query { pizzeria { getPizza } }`
+    #[default]
+    Services,
+    /// Root fields named after their service and method: `query { pizzeriaGetPizza }`
+    Flat,
+}
+
+/// Whether enum values keep the prefix the proto style guide gives them, made of the enum name in
+/// SCREAMING_SNAKE_CASE.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EnumPrefix {
+    /// `FAT_TYPE_GOOD` stays `FAT_TYPE_GOOD`
+    #[default]
+    Keep,
+    /// `FAT_TYPE_GOOD` becomes `GOOD`, unless that leaves an invalid name or two values with the
+    /// same name
+    Strip,
+}
+
+/// What becomes of the zero value of an enum named `UNSPECIFIED` or `<PREFIX>_UNSPECIFIED`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Unspecified {
+    /// It is a value like the others
+    #[default]
+    Keep,
+    /// It is left out of the enum, and fields holding it are null
+    Null,
+}
+
+/// Which messages and enums of the generated files are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Types {
+    /// The ones the methods use: object types for the messages reachable from the method
+    /// outputs, input types for the ones reachable from the method inputs
+    #[default]
+    Reachable,
+    /// All of them, each message as both an object and an input type
+    All,
+}
+
+/// How `oneof` groups are rendered in object types. Input types always use a `@oneOf` input
+/// object.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OneofStyle {
+    /// Every member is a nullable field, next to a field holding the `<Oneof>Case` enum of the
+    /// member that is set
+    #[default]
+    Fields,
+    /// A single field holding a union of one object type per member
+    Union,
+}
+
+/// How map fields are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum MapStyle {
+    /// A list of `<Message><Field>Entry` objects with a `key` and a `value` field
+    #[default]
+    Entries,
+    /// A `JSON` object
+    Json,
+}
+
+/// The grpc library the generated resolvers load the services with.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Grpc {
+    /// `@grpc/grpc-js`, with the proto files loaded by `@grpc/proto-loader`
+    #[default]
+    GrpcJs,
+    /// The deprecated `grpc` package and its `grpc.load`
+    Legacy,
+}
+
+/// The GraphQL scalar each proto scalar type maps to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalarMapping {
+    pub bool_: String,
+    pub string: String,
+    pub int32: String,
+    pub int64: String,
+    pub uint32: String,
+    pub uint64: String,
+    pub float: String,
+    pub double: String,
+    pub bytes: String,
+    pub sint32: String,
+    pub sint64: String,
+    pub fixed32: String,
+    pub fixed64: String,
+    pub sfixed32: String,
+    pub sfixed64: String,
+}
+
+impl Default for ScalarMapping {
+    fn default() -> ScalarMapping {
+        ScalarMapping {
+            bool_: "Boolean".to_string(),
+            string: "String".to_string(),
+            int32: "Int".to_string(),
+            // GraphQL's Int is a signed 32 bit integer, so wider and unsigned integers need
+            // custom scalars to avoid overflows.
+            int64: "Int64".to_string(),
+            uint32: "UInt32".to_string(),
+            uint64: "UInt64".to_string(),
+            float: "Float".to_string(),
+            double: "Float".to_string(),
+            bytes: "Base64".to_string(),
+            sint32: "Int".to_string(),
+            sint64: "Int64".to_string(),
+            fixed32: "UInt32".to_string(),
+            fixed64: "UInt64".to_string(),
+            sfixed32: "Int".to_string(),
+            sfixed64: "Int64".to_string(),
+        }
+    }
+}
+
+impl ScalarMapping {
+    /// The GraphQL scalar for a proto scalar type, `None` for messages, groups and enums.
+    pub fn get(&self, proto_type: FieldDescriptorProto_Type) -> Option<&str> {
+        use self::FieldDescriptorProto_Type::*;
+
+        let scalar = match proto_type {
+            TYPE_BOOL => &self.bool_,
+            TYPE_STRING => &self.string,
+            TYPE_INT32 => &self.int32,
+            TYPE_INT64 => &self.int64,
+            TYPE_UINT32 => &self.uint32,
+            TYPE_UINT64 => &self.uint64,
+            TYPE_FLOAT => &self.float,
+            TYPE_DOUBLE => &self.double,
+            TYPE_BYTES => &self.bytes,
+            TYPE_SINT32 => &self.sint32,
+            TYPE_SINT64 => &self.sint64,
+            TYPE_FIXED32 => &self.fixed32,
+            TYPE_FIXED64 => &self.fixed64,
+            TYPE_SFIXED32 => &self.sfixed32,
+            TYPE_SFIXED64 => &self.sfixed64,
+            TYPE_MESSAGE | TYPE_ENUM | TYPE_GROUP => return None,
+        };
+
+        Some(scalar.as_str())
+    }
+
+    fn get_mut(&mut self, proto_type_name: &str) -> Option<&mut String> {
+        match proto_type_name {
+            "bool" => Some(&mut self.bool_),
+            "string" => Some(&mut self.string),
+            "int32" => Some(&mut self.int32),
+            "int64" => Some(&mut self.int64),
+            "uint32" => Some(&mut self.uint32),
+            "uint64" => Some(&mut self.uint64),
+            "float" => Some(&mut self.float),
+            "double" => Some(&mut self.double),
+            "bytes" => Some(&mut self.bytes),
+            "sint32" => Some(&mut self.sint32),
+            "sint64" => Some(&mut self.sint64),
+            "fixed32" => Some(&mut self.fixed32),
+            "fixed64" => Some(&mut self.fixed64),
+            "sfixed32" => Some(&mut self.sfixed32),
+            "sfixed64" => Some(&mut self.sfixed64),
+            _ => None,
+        }
+    }
+
+    /// The first two options mapping proto types with different JS implementations to the same
+    /// custom scalar, which only has one implementation.
+    fn conflict(&self) -> Option<ConfigError> {
+        // Grouped as in the JS implementations: integers by range, bytes as base64, and
+        // everything else passed through.
+        let options = [
+            ("bool", "value", &self.bool_),
+            ("string", "value", &self.string),
+            ("float", "value", &self.float),
+            ("double", "value", &self.double),
+            ("bytes", "bytes", &self.bytes),
+            ("int32", "int32", &self.int32),
+            ("sint32", "int32", &self.sint32),
+            ("sfixed32", "int32", &self.sfixed32),
+            ("uint32", "uint32", &self.uint32),
+            ("fixed32", "uint32", &self.fixed32),
+            ("int64", "int64", &self.int64),
+            ("sint64", "int64", &self.sint64),
+            ("sfixed64", "int64", &self.sfixed64),
+            ("uint64", "uint64", &self.uint64),
+            ("fixed64", "uint64", &self.fixed64),
+        ];
+
+        options
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(_, _, scalar))| !BUILTIN_SCALARS.contains(&scalar.as_str()))
+            .flat_map(|(i, first)| options[i + 1..].iter().map(move |second| (first, second)))
+            .find(|&(first, second)| first.2 == second.2 && first.1 != second.1)
+            .map(|(first, second)| ConfigError::ConflictingScalars {
+                first: format!("scalar.{}", first.0),
+                second: format!("scalar.{}", second.0),
+                scalar: first.2.clone(),
+            })
+    }
+}
+
+/// The generator configuration, parsed from the plugin parameter (`--apollo_opt`).
+///
+/// The parameter is a comma-separated list of `key=value` pairs, for example
+/// `outputs=schema+resolvers,field_case=camel,scalar.int64=String`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub outputs: Outputs,
+    pub emit: Emit,
+    pub field_case: FieldCase,
+    pub type_naming: TypeNaming,
+    pub types: Types,
+    pub root_fields: RootFields,
+    pub enum_prefix: EnumPrefix,
+    pub unspecified: Unspecified,
+    pub oneof_style: OneofStyle,
+    pub map_style: MapStyle,
+    pub scalars: ScalarMapping,
+    pub comments: Comments,
+    /// Methods whose name starts with one of these are queries, when neither their options nor
+    /// their idempotency level tell.
+    pub query_prefixes: Vec<String>,
+    /// The field number of a `MethodOptions` extension whose value says whether a method is a
+    /// query (1) or a mutation (2), overriding everything else.
+    pub operation_option: Option<u32>,
+    pub grpc: Grpc,
+    /// The directories `@grpc/proto-loader` looks for the proto files and their imports in.
+    pub include_paths: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            outputs: Outputs::default(),
+            emit: Emit::default(),
+            field_case: FieldCase::default(),
+            type_naming: TypeNaming::default(),
+            types: Types::default(),
+            root_fields: RootFields::default(),
+            enum_prefix: EnumPrefix::default(),
+            unspecified: Unspecified::default(),
+            oneof_style: OneofStyle::default(),
+            map_style: MapStyle::default(),
+            scalars: ScalarMapping::default(),
+            comments: Comments::default(),
+            query_prefixes: vec!["Get".to_string(), "List".to_string()],
+            operation_option: None,
+            grpc: Grpc::default(),
+            include_paths: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    UnknownKey(String),
+    MissingValue(String),
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    /// Two `scalar.<proto type>` options giving a custom scalar two different implementations.
+    ConflictingScalars {
+        first: String,
+        second: String,
+        scalar: String,
+    },
+}
+
+impl ::std::fmt::Display for ConfigError {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            ConfigError::UnknownKey(ref key) => write!(formatter, "unknown option `{}`", key),
+            ConfigError::MissingValue(ref key) => {
+                write!(
+                    formatter,
+                    "option `{}` expects a value (`{}=...`)",
+                    key, key
+                )
+            }
+            ConfigError::InvalidValue {
+                ref key,
+                ref value,
+                expected,
+            } => write!(
+                formatter,
+                "invalid value `{}` for option `{}`, expected {}",
+                value, key, expected
+            ),
+            ConfigError::ConflictingScalars {
+                ref first,
+                ref second,
+                ref scalar,
+            } => write!(
+                formatter,
+                "options `{}` and `{}` both map to `{}`, which can only validate the values of one of them",
+                first, second, scalar
+            ),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_parameter(parameter: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+
+        for pair in parameter
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            let mut parts = pair.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = match parts.next() {
+                Some(value) => value.trim(),
+                None => return Err(ConfigError::MissingValue(key.to_string())),
+            };
+            config.set(key, value)?;
+        }
+
+        match config.scalars.conflict() {
+            Some(error) => Err(error),
+            None => Ok(config),
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = |expected| ConfigError::InvalidValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            expected,
+        };
+
+        match key {
+            "outputs" => {
+                let mut outputs = Outputs {
+                    schema: false,
+                    type_defs: false,
+                    resolvers: false,
+                };
+                for output in value.split('+') {
+                    match output {
+                        "schema" => outputs.schema = true,
+                        "type_defs" => outputs.type_defs = true,
+                        "resolvers" => outputs.resolvers = true,
+                        _ => {
+                            return Err(invalid(
+                                "a `+`-separated list of schema, type_defs and resolvers",
+                            ))
+                        }
+                    }
+                }
+                self.outputs = outputs;
+            }
+            "emit" => {
+                self.emit = match value {
+                    "per_file" => Emit::PerFile,
+                    "merged" => Emit::Merged,
+                    _ => return Err(invalid("one of per_file or merged")),
+                }
+            }
+            "field_case" => {
+                self.field_case = match value {
+                    "json" => FieldCase::Json,
+                    "preserve" => FieldCase::Preserve,
+                    "camel" => FieldCase::Camel,
+                    "snake" => FieldCase::Snake,
+                    _ => return Err(invalid("one of json, preserve, camel or snake")),
+                }
+            }
+            "type_naming" => {
+                self.type_naming = match value {
+                    "package" => TypeNaming::Package,
+                    "strip_common_prefix" => TypeNaming::StripCommonPrefix,
+                    "short" => TypeNaming::Short,
+                    _ => return Err(invalid("one of package, strip_common_prefix or short")),
+                }
+            }
+            "types" => {
+                self.types = match value {
+                    "reachable" => Types::Reachable,
+                    "all" => Types::All,
+                    _ => return Err(invalid("one of reachable or all")),
+                }
+            }
+            "root_fields" => {
+                self.root_fields = match value {
+                    "services" => RootFields::Services,
+                    "flat" => RootFields::Flat,
+                    _ => return Err(invalid("one of services or flat")),
+                }
+            }
+            "enum_prefix" => {
+                self.enum_prefix = match value {
+                    "keep" => EnumPrefix::Keep,
+                    "strip" => EnumPrefix::Strip,
+                    _ => return Err(invalid("one of keep or strip")),
+                }
+            }
+            "unspecified" => {
+                self.unspecified = match value {
+                    "keep" => Unspecified::Keep,
+                    "null" => Unspecified::Null,
+                    _ => return Err(invalid("one of keep or null")),
+                }
+            }
+            "oneof" => {
+                self.oneof_style = match value {
+                    "fields" => OneofStyle::Fields,
+                    "union" => OneofStyle::Union,
+                    _ => return Err(invalid("one of fields or union")),
+                }
+            }
+            "map" => {
+                self.map_style = match value {
+                    "entries" => MapStyle::Entries,
+                    "json" => MapStyle::Json,
+                    _ => return Err(invalid("one of entries or json")),
+                }
+            }
+            "comments" => {
+                let mut comments = Comments {
+                    leading: false,
+                    trailing: false,
+                    detached: false,
+                };
+                for kind in value.split('+').filter(|_| value != "none") {
+                    match kind {
+                        "leading" => comments.leading = true,
+                        "trailing" => comments.trailing = true,
+                        "detached" => comments.detached = true,
+                        _ => {
+                            return Err(invalid(
+                                "none or a `+`-separated list of leading, trailing and detached",
+                            ))
+                        }
+                    }
+                }
+                self.comments = comments;
+            }
+            "query_prefixes" => {
+                self.query_prefixes = value
+                    .split('+')
+                    .filter(|prefix| !prefix.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "operation_option" => {
+                self.operation_option = match value.parse() {
+                    Ok(number) if number > 0 => Some(number),
+                    _ => return Err(invalid("a field number")),
+                }
+            }
+            "grpc" => {
+                self.grpc = match value {
+                    "grpc_js" => Grpc::GrpcJs,
+                    "legacy" => Grpc::Legacy,
+                    _ => return Err(invalid("one of grpc_js or legacy")),
+                }
+            }
+            "include_paths" => {
+                self.include_paths = value
+                    .split('+')
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            _ if key.starts_with("scalar.") => {
+                if value.is_empty() {
+                    return Err(invalid("a GraphQL type name"));
+                }
+                match self.scalars.get_mut(&key["scalar.".len()..]) {
+                    Some(scalar) => *scalar = value.to_string(),
+                    None => return Err(ConfigError::UnknownKey(key.to_string())),
+                }
+            }
+            _ => return Err(ConfigError::UnknownKey(key.to_string())),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_parameter_is_default_config() {
+        assert_eq!(Config::from_parameter("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn parameter_parsing_works() {
+        let config = Config::from_parameter(concat!(
+            "outputs=schema+resolvers,emit=merged, field_case=camel,type_naming=short,",
+            "types=all,root_fields=flat,enum_prefix=strip,unspecified=null,oneof=union,",
+            "map=json,scalar.int64=String,query_prefixes=Get+Search,operation_option=50001,",
+            "comments=leading+detached,grpc=legacy,include_paths=proto+vendor/proto"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.outputs,
+            Outputs {
+                schema: true,
+                type_defs: false,
+                resolvers: true,
+            }
+        );
+        assert_eq!(config.emit, Emit::Merged);
+        assert_eq!(config.field_case, FieldCase::Camel);
+        assert_eq!(config.type_naming, TypeNaming::Short);
+        assert_eq!(config.types, Types::All);
+        assert_eq!(config.root_fields, RootFields::Flat);
+        assert_eq!(config.enum_prefix, EnumPrefix::Strip);
+        assert_eq!(config.unspecified, Unspecified::Null);
+        assert_eq!(config.oneof_style, OneofStyle::Union);
+        assert_eq!(config.map_style, MapStyle::Json);
+        assert_eq!(config.query_prefixes, vec!["Get", "Search"]);
+        assert_eq!(config.operation_option, Some(50001));
+        assert_eq!(config.grpc, Grpc::Legacy);
+        assert_eq!(config.include_paths, vec!["proto", "vendor/proto"]);
+        assert_eq!(
+            config.comments,
+            Comments {
+                leading: true,
+                trailing: false,
+                detached: true,
+            }
+        );
+        assert_eq!(
+            Config::from_parameter("comments=none").unwrap().comments,
+            Comments {
+                leading: false,
+                trailing: false,
+                detached: false,
+            }
+        );
+        assert_eq!(
+            config.scalars.get(FieldDescriptorProto_Type::TYPE_INT64),
+            Some("String")
+        );
+    }
+
+    #[test]
+    fn parameter_parsing_errors() {
+        assert_eq!(
+            Config::from_parameter("colour=blue"),
+            Err(ConfigError::UnknownKey("colour".to_string()))
+        );
+        assert_eq!(
+            Config::from_parameter("scalar.int128=String"),
+            Err(ConfigError::UnknownKey("scalar.int128".to_string()))
+        );
+        assert_eq!(
+            Config::from_parameter("field_case"),
+            Err(ConfigError::MissingValue("field_case".to_string()))
+        );
+        assert_eq!(
+            Config::from_parameter("field_case=kebab").unwrap_err().to_string(),
+            "invalid value `kebab` for option `field_case`, expected one of json, preserve, camel or snake"
+        );
+    }
+
+    #[test]
+    fn conflicting_scalars_are_rejected() {
+        assert_eq!(
+            Config::from_parameter("scalar.uint64=BigInt,scalar.int64=BigInt")
+                .unwrap_err()
+                .to_string(),
+            "options `scalar.int64` and `scalar.uint64` both map to `BigInt`, which can only validate the values of one of them"
+        );
+        // Types with the same implementation, or mapped to builtin scalars, can share them.
+        assert!(Config::from_parameter("scalar.int64=BigInt,scalar.sfixed64=BigInt").is_ok());
+        assert!(Config::from_parameter("scalar.uint64=String,scalar.bytes=String").is_ok());
+    }
+}
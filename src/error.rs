@@ -0,0 +1,202 @@
+use config::ConfigError;
+
+/// A position in a proto source file, zero-based as in `SourceCodeInfo`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start_line: i32,
+    pub start_column: i32,
+    pub end_line: i32,
+    pub end_column: i32,
+}
+
+impl Span {
+    /// See the `span` field of `SourceCodeInfo.Location` in descriptor.proto: it is either
+    /// `[start_line, start_column, end_line, end_column]` or `[start_line, start_column, end_column]`.
+    pub fn from_proto(span: &[i32]) -> Option<Span> {
+        match *span {
+            [start_line, start_column, end_line, end_column] => Some(Span {
+                start_line,
+                start_column,
+                end_line,
+                end_column,
+            }),
+            [line, start_column, end_column] => Some(Span {
+                start_line: line,
+                start_column,
+                end_line: line,
+                end_column,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Where in the proto sources an error originates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub file_name: String,
+    /// The fully qualified name of the proto element, without the leading dot.
+    pub element: String,
+    /// Only available when protoc was run with source info (it always is for plugins).
+    pub span: Option<Span>,
+}
+
+impl Location {
+    pub fn new(file_name: &str, element: &str, span: Option<Span>) -> Location {
+        Location {
+            file_name: file_name.to_string(),
+            element: element.to_string(),
+            span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    Config(ConfigError),
+    /// A `graphql` option that is malformed or does not apply where it is set.
+    InvalidOption(String),
+    /// A reference to a message or an enum that is skipped with the `graphql` options, by its
+    /// fully qualified name.
+    SkippedType(String),
+    /// Two messages or enums, by their fully qualified names, given the same GraphQL name.
+    NameCollision {
+        name: String,
+        first: String,
+        second: String,
+    },
+    /// Two services or methods, by their fully qualified names, given the same field of a root
+    /// or service type, as `Type.field`.
+    FieldCollision {
+        field: String,
+        first: String,
+        second: String,
+    },
+    /// A field whose values the `grpc=legacy` loader gives in a form the resolvers cannot
+    /// convert, with what the loader does.
+    LegacyGrpc(&'static str),
+    /// Rendering the GraphQL or JS output failed.
+    Format,
+}
+
+/// A generation failure, reported to protoc through `CodeGeneratorResponse.error`.
+///
+/// Displays as `file.proto:line:column: element: message`, with one-based lines and columns like
+/// protoc's own diagnostics.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub location: Option<Box<Location>>,
+}
+
+impl Error {
+    pub fn at(kind: ErrorKind, location: Location) -> Error {
+        Error {
+            kind,
+            location: Some(Box::new(location)),
+        }
+    }
+}
+
+impl ::std::fmt::Display for ErrorKind {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match *self {
+            ErrorKind::Config(ref err) => {
+                write!(formatter, "invalid --apollo_opt parameter: {}", err)
+            }
+            ErrorKind::InvalidOption(ref message) => write!(formatter, "{}", message),
+            ErrorKind::SkippedType(ref type_name) => write!(
+                formatter,
+                "references {}, which is skipped with the graphql options",
+                type_name
+            ),
+            ErrorKind::NameCollision {
+                ref name,
+                ref first,
+                ref second,
+            } => write!(
+                formatter,
+                "{} and {} are both named {} in the GraphQL schema, rename one of them with the \
+                 graphql options or use another type_naming",
+                first, second, name
+            ),
+            ErrorKind::FieldCollision {
+                ref field,
+                ref first,
+                ref second,
+            } => write!(
+                formatter,
+                "{} and {} are both the {} field in the GraphQL schema, rename one of them with \
+                 the graphql options",
+                first, second, field
+            ),
+            ErrorKind::LegacyGrpc(what) => write!(
+                formatter,
+                "the grpc=legacy loader {}, which the resolvers cannot convert, use grpc=grpc_js \
+                 or skip the field with the graphql options",
+                what
+            ),
+            ErrorKind::Format => write!(formatter, "failed to render the generated code"),
+        }
+    }
+}
+
+impl ::std::fmt::Display for Error {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        if let Some(ref location) = self.location {
+            write!(formatter, "{}", location.file_name)?;
+            if let Some(ref span) = location.span {
+                write!(
+                    formatter,
+                    ":{}:{}",
+                    span.start_line + 1,
+                    span.start_column + 1
+                )?;
+            }
+            write!(formatter, ": {}: ", location.element)?;
+        }
+        write!(formatter, "{}", self.kind)
+    }
+}
+
+impl ::std::error::Error for Error {}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Error {
+        Error {
+            kind: ErrorKind::Config(err),
+            location: None,
+        }
+    }
+}
+
+impl From<::std::fmt::Error> for Error {
+    fn from(_: ::std::fmt::Error) -> Error {
+        Error {
+            kind: ErrorKind::Format,
+            location: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_display_includes_location() {
+        let err = Error::at(
+            ErrorKind::SkippedType("pizzeria.Extra".to_string()),
+            Location::new(
+                "pizza.proto",
+                "pizzeria.Pizza.extras",
+                Span::from_proto(&[11, 4, 24]),
+            ),
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "pizza.proto:12:5: pizzeria.Pizza.extras: references pizzeria.Extra, which is skipped with the graphql options"
+        );
+    }
+}
@@ -1,12 +1,20 @@
+use config::Config;
+use error::Error;
 use gen;
+use gen_response;
+use options;
+use support;
 
-use protobuf::descriptor::*;
 use protobuf::compiler_plugin::GenResult;
+use protobuf::descriptor::*;
+use protobuf::plugin::CodeGeneratorRequest;
 use protobuf::repeated::RepeatedField;
+use protobuf::{CodedOutputStream, Message, UnknownFields};
 
 struct Project {
     file_descriptors: Vec<FileDescriptorProto>,
     files_to_generate: Vec<String>,
+    parameter: String,
     expected_results: Vec<GenResult>,
 }
 
@@ -15,10 +23,17 @@ impl Project {
         Project {
             file_descriptors: Vec::new(),
             files_to_generate: Vec::new(),
+            parameter: String::new(),
             expected_results: Vec::new(),
         }
     }
 
+    /// The plugin parameter, as passed with `--apollo_opt`.
+    fn parameter(mut self, parameter: &str) -> Self {
+        self.parameter = parameter.to_string();
+        self
+    }
+
     fn source_file(mut self, file: FileDescriptorProto) -> Self {
         self.file_descriptors.push(file);
         self
@@ -37,8 +52,23 @@ impl Project {
         self
     }
 
+    /// Generates the target files with a plugin parameter enabling a single output, and returns
+    /// its content.
+    fn generate(&self, parameter: &str) -> String {
+        self.try_generate(parameter).unwrap()
+    }
+
+    /// Like `generate`, with the error of a failed generation.
+    fn try_generate(&self, parameter: &str) -> Result<String, Error> {
+        let config = Config::from_parameter(parameter).unwrap();
+        let generated = gen(&self.file_descriptors, &self.files_to_generate, &config)?;
+        assert_eq!(generated.len(), 1);
+        Ok(String::from_utf8(generated[0].content.clone()).unwrap())
+    }
+
     fn unwrap(&self) {
-        let generated = gen(&self.file_descriptors, &self.files_to_generate);
+        let config = Config::from_parameter(&self.parameter).unwrap();
+        let generated = gen(&self.file_descriptors, &self.files_to_generate, &config).unwrap();
         assert_eq!(generated.len(), self.expected_results.len());
         for expected in self.expected_results.iter() {
             assert!(
@@ -63,6 +93,7 @@ impl Project {
 #[test]
 fn it_works_for_basic_types() {
     let mut file = FileDescriptorProto::new();
+    file.set_name("meh".into());
     let mut messages = RepeatedField::new();
     let mut pizza = DescriptorProto::new();
     pizza.set_name("Pizza".into());
@@ -75,6 +106,7 @@ fn it_works_for_basic_types() {
     Project::new()
         .source_file(file)
         .target_file("meh")
+        .parameter("outputs=schema,types=all")
         .expect(
             "meh.out",
             r##"type Pizza {
@@ -87,31 +119,2273 @@ type Topping {
 }
 
 input ToppingInput {
-}
-
-"##,
+}"##,
         )
         .unwrap();
 }
 
 #[test]
 fn empty_proto_source_file() {
-    let file = FileDescriptorProto::new();
+    let mut file = FileDescriptorProto::new();
+    file.set_name("meh".into());
     Project::new()
         .source_file(file)
         .target_file("meh")
+        .parameter("outputs=schema+type_defs")
         .expect("meh.out", "")
-        .expect("meh.js", r##"export const Query = `
+        .expect(
+            "meh-type-defs.js",
+            r##"module.exports = [
+]
+"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn type_defs_match_the_schema_without_services() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.mut_field().push(field("title", 1, TYPE_STRING, ""));
+    file.mut_message_type().push(pizza);
+
+    let project = Project::new().source_file(file).target_file("pizza.proto");
+    let schema = project.generate("outputs=schema,types=all");
+    assert!(!schema.contains("Query"));
+
+    // The type definitions hold the schema, a template literal per type.
+    let type_defs = project.generate("outputs=type_defs,types=all");
+    let definitions: Vec<&str> = type_defs
+        .split(" = `\n")
+        .skip(1)
+        .map(|definition| definition.split("\n`").next().unwrap())
+        .collect();
+    assert_eq!(definitions.join("\n\n"), schema.trim_end());
+}
+
+fn field(
+    name: &str,
+    number: i32,
+    field_type: FieldDescriptorProto_Type,
+    type_name: &str,
+) -> FieldDescriptorProto {
+    let mut field = FieldDescriptorProto::new();
+    field.set_name(name.to_string());
+    field.set_number(number);
+    field.set_label(FieldDescriptorProto_Label::LABEL_OPTIONAL);
+    field.set_field_type(field_type);
+    if !type_name.is_empty() {
+        field.set_type_name(type_name.to_string());
+    }
+    field
+}
+
+fn required_field(
+    name: &str,
+    number: i32,
+    field_type: FieldDescriptorProto_Type,
+) -> FieldDescriptorProto {
+    let mut field = field(name, number, field_type, "");
+    field.set_label(FieldDescriptorProto_Label::LABEL_REQUIRED);
+    field
+}
+
+/// Sets the `graphql` option of a descriptor options message, given the string and varint fields
+/// of the option message.
+fn graphql_options(
+    unknown_fields: &mut UnknownFields,
+    strings: &[(u32, &str)],
+    varints: &[(u32, u64)],
+) {
+    let mut option = UnknownFields::new();
+    for &(number, value) in strings {
+        option.add_length_delimited(number, value.as_bytes().to_vec());
+    }
+    for &(number, value) in varints {
+        option.add_varint(number, value);
+    }
+
+    let mut bytes = Vec::new();
+    {
+        let mut output = CodedOutputStream::vec(&mut bytes);
+        output.write_unknown_fields(&option).unwrap();
+        output.flush().unwrap();
+    }
+    unknown_fields.add_length_delimited(options::EXTENSION_NUMBER, bytes);
+}
+
+#[test]
+fn all_scalar_types_and_groups() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("search.proto".into());
+    let mut result = DescriptorProto::new();
+    result.set_name("Result".into());
+    result.mut_field().push(field("url", 1, TYPE_STRING, ""));
+
+    let mut search = DescriptorProto::new();
+    search.set_name("Search".into());
+    search.set_field(RepeatedField::from_vec(vec![
+        field("cursor", 1, TYPE_BYTES, ""),
+        required_field("total", 2, TYPE_SINT64),
+        field("offset", 3, TYPE_SINT32, ""),
+        field("checksum", 4, TYPE_FIXED32, ""),
+        field("id", 5, TYPE_SFIXED64, ""),
+        field("result", 6, TYPE_GROUP, ".Search.Result"),
+    ]));
+    search.mut_nested_type().push(result);
+    file.mut_message_type().push(search);
+
+    Project::new()
+        .source_file(file)
+        .target_file("search.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "search.proto.out",
+            r##"scalar Base64
+
+scalar Int64
+
+scalar UInt32
+
+type Search {
+  cursor: Base64
+  total: Int64!
+  offset: Int
+  checksum: UInt32
+  id: Int64
+  result: SearchResult
+}
+
+input SearchInput {
+  cursor: Base64
+  total: Int64
+  offset: Int
+  checksum: UInt32
+  id: Int64
+  result: SearchResultInput
+}
+
+type SearchResult {
+  url: String
+}
+
+input SearchResultInput {
+  url: String
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn integer_scalars_are_implemented_in_resolvers() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("ledger.proto".into());
+    let mut entry = DescriptorProto::new();
+    entry.set_name("Entry".into());
+    entry.set_field(RepeatedField::from_vec(vec![
+        field("balance", 1, TYPE_INT64, ""),
+        field("serial", 2, TYPE_UINT64, ""),
+        field("checksum", 3, TYPE_FIXED32, ""),
+    ]));
+    file.mut_message_type().push(entry);
+
+    let resolvers = Project::new()
+        .source_file(file)
+        .target_file("ledger.proto")
+        .generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains("const parseInteger = (scalar, min, max) => value => {\n"));
+    // 64 bit integers stay strings, 32 bit ones become numbers.
+    assert!(resolvers.contains(
+        "const parseInt64Value = parseInteger('Int64', '-9223372036854775808', '9223372036854775807')\n"
+    ));
+    assert!(resolvers.contains(
+        "const parseUInt64Value = parseInteger('UInt64', '0', '18446744073709551615')\n"
+    ));
+    assert!(resolvers.contains(
+        "const parseUInt32Value = value => Number(parseInteger('UInt32', '0', '4294967295')(value))\n"
+    ));
+    assert!(resolvers.contains(concat!(
+        "  Int64: new GraphQLScalarType({\n",
+        "    name: 'Int64',\n",
+        "    serialize: value => value.toString(),\n",
+        "    parseValue: parseInt64Value,\n",
+        "    parseLiteral: ast => ast.kind === Kind.INT || ast.kind === Kind.STRING ? ",
+        "parseInt64Value(ast.value) : undefined,\n",
+        "  }),\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "  UInt32: new GraphQLScalarType({\n",
+        "    name: 'UInt32',\n",
+        "    serialize: value => Number(value),\n",
+        "    parseValue: parseUInt32Value,\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "  UInt64: new GraphQLScalarType({\n",
+        "    name: 'UInt64',\n",
+        "    serialize: value => value.toString(),\n",
+        "    parseValue: parseUInt64Value,\n",
+    )));
+}
+
+#[test]
+fn well_known_types() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut timestamp_file = FileDescriptorProto::new();
+    timestamp_file.set_name("google/protobuf/timestamp.proto".into());
+    timestamp_file.set_package("google.protobuf".into());
+    let mut timestamp = DescriptorProto::new();
+    timestamp.set_name("Timestamp".into());
+    timestamp.set_field(RepeatedField::from_vec(vec![
+        field("seconds", 1, TYPE_INT64, ""),
+        field("nanos", 2, TYPE_INT32, ""),
+    ]));
+    timestamp_file.mut_message_type().push(timestamp);
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("baked_at", 1, TYPE_MESSAGE, ".google.protobuf.Timestamp"),
+        field("extras", 2, TYPE_MESSAGE, ".google.protobuf.Struct"),
+        field("update_mask", 3, TYPE_MESSAGE, ".google.protobuf.FieldMask"),
+    ]));
+    file.mut_message_type().push(pizza);
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut clean = MethodDescriptorProto::new();
+    clean.set_name("Clean".into());
+    clean.set_input_type(".google.protobuf.Empty".into());
+    clean.set_output_type(".google.protobuf.Empty".into());
+    oven.mut_method().push(clean);
+    file.mut_service().push(oven);
+
+    Project::new()
+        .source_file(timestamp_file)
+        .source_file(file)
+        .target_file("oven.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "oven.proto.out",
+            r##"scalar DateTime
+
+scalar JSON
+
+type Pizza {
+  bakedAt: DateTime
+  extras: JSON
+  updateMask: [String!]
+}
+
+input PizzaInput {
+  bakedAt: DateTime
+  extras: JSON
+  updateMask: [String!]
+}
+
+type OvenServiceMutations {
+  clean: Boolean!
+}
+
 type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
 }
-`
-export const typeDefsWithoutQuery = [
-]
 
-export const typeDefs = [
-  Query,
-]
+type Mutation {
+  oven: OvenServiceMutations!
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn well_known_types_are_converted_in_resolvers() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut bake = DescriptorProto::new();
+    bake.set_name("Bake".into());
+    bake.set_field(RepeatedField::from_vec(vec![
+        field("started_at", 1, TYPE_MESSAGE, ".google.protobuf.Timestamp"),
+        field("time", 2, TYPE_MESSAGE, ".google.protobuf.Duration"),
+        field("settings", 3, TYPE_MESSAGE, ".google.protobuf.Struct"),
+        field("setting", 4, TYPE_MESSAGE, ".google.protobuf.Value"),
+        field("steps", 5, TYPE_MESSAGE, ".google.protobuf.ListValue"),
+        field("recipe", 6, TYPE_MESSAGE, ".google.protobuf.Any"),
+        field("update_mask", 7, TYPE_MESSAGE, ".google.protobuf.FieldMask"),
+    ]));
+    file.mut_message_type().push(bake);
+    let project = Project::new().source_file(file).target_file("oven.proto");
+
+    // The JSON scalar tells how it holds Any messages.
+    let schema = project.generate("outputs=schema,types=all");
+    assert!(schema.contains(concat!(
+        "\"\"\"Any JSON value. A `google.protobuf.Any` is an object with the URL of its type under ",
+        "\"@type\" and its base64-encoded message under \"value\".\"\"\"\nscalar JSON\n",
+    )));
+
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains("const wellKnownTypes = {\n"));
+    assert!(resolvers.contains(concat!(
+        "  Bake: message => message == null ? null : ({\n",
+        "    startedAt: wellKnownTypes.fromTimestamp(message.started_at),\n",
+        "    time: wellKnownTypes.fromDuration(message.time),\n",
+        "    settings: wellKnownTypes.fromStruct(message.settings),\n",
+        "    setting: wellKnownTypes.fromValue(message.setting),\n",
+        "    steps: wellKnownTypes.fromListValue(message.steps),\n",
+        "    recipe: wellKnownTypes.fromAny(message.recipe),\n",
+        "    updateMask: wellKnownTypes.fromFieldMask(message.update_mask),\n",
+        "  }),\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "  Bake: input => input == null ? null : ({\n",
+        "    started_at: wellKnownTypes.toTimestamp(input.startedAt),\n",
+        "    time: wellKnownTypes.toDuration(input.time),\n",
+        "    settings: wellKnownTypes.toStruct(input.settings),\n",
+        "    setting: wellKnownTypes.toValue(input.setting),\n",
+        "    steps: wellKnownTypes.toListValue(input.steps),\n",
+        "    recipe: wellKnownTypes.toAny(input.recipe),\n",
+        "    update_mask: wellKnownTypes.toFieldMask(input.updateMask),\n",
+        "  }),\n",
+    )));
+    assert!(resolvers.contains("    parseLiteral: ast => ast.kind === Kind.STRING ? parseDateTimeValue(ast.value) : undefined,\n"));
+    assert!(resolvers.contains("    parseLiteral: parseJSONLiteral,\n"));
+}
+
+#[test]
+fn wrapper_types_are_nullable_scalars() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut wrappers_file = FileDescriptorProto::new();
+    wrappers_file.set_name("google/protobuf/wrappers.proto".into());
+    wrappers_file.set_package("google.protobuf".into());
+    let mut string_value = DescriptorProto::new();
+    string_value.set_name("StringValue".into());
+    string_value.set_field(RepeatedField::from_vec(vec![field(
+        "value",
+        1,
+        TYPE_STRING,
+        "",
+    )]));
+    wrappers_file.mut_message_type().push(string_value);
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("name", 1, TYPE_MESSAGE, ".google.protobuf.StringValue"),
+        field("weight", 2, TYPE_MESSAGE, ".google.protobuf.Int64Value"),
+        field("vegan", 3, TYPE_MESSAGE, ".google.protobuf.BoolValue"),
+        field("slices", 4, TYPE_INT32, ""),
+    ]));
+    file.mut_message_type().push(pizza);
+
+    let project = Project::new()
+        .source_file(wrappers_file)
+        .source_file(file)
+        .target_file("pizza.proto");
+    // The resolvers unwrap the values, and wrap them back.
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains(concat!(
+        "    name: wellKnownTypes.fromWrapper(message.name),\n",
+        "    weight: wellKnownTypes.fromWrapper(message.weight),\n",
+        "    vegan: wellKnownTypes.fromWrapper(message.vegan),\n",
+        "    slices: message.slices ?? 0,\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "    name: wellKnownTypes.toWrapper(input.name),\n",
+        "    weight: wellKnownTypes.toWrapper(input.weight),\n",
+        "    vegan: wellKnownTypes.toWrapper(input.vegan),\n",
+        "    slices: input.slices,\n",
+    )));
+    assert!(
+        resolvers.contains("  fromWrapper: wrapper => wrapper == null ? null : wrapper.value,\n")
+    );
+
+    project
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"scalar Int64
+
+type Pizza {
+  name: String
+  weight: Int64
+  vegan: Boolean
+  slices: Int!
+}
+
+input PizzaInput {
+  name: String
+  weight: Int64
+  vegan: Boolean
+  slices: Int
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn oneofs() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    let mut base = OneofDescriptorProto::new();
+    base.set_name("base".into());
+    pizza.mut_oneof_decl().push(base);
+    let mut tomato_sauce = field("tomato_sauce", 2, TYPE_STRING, "");
+    tomato_sauce.set_oneof_index(0);
+    let mut cream = field("cream", 3, TYPE_MESSAGE, ".Cream");
+    cream.set_oneof_index(0);
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("name", 1, TYPE_STRING, ""),
+        tomato_sauce,
+        cream,
+    ]));
+    file.mut_message_type().push(pizza);
+    let mut cream = DescriptorProto::new();
+    cream.set_name("Cream".into());
+    cream.mut_field().push(field("fat", 1, TYPE_INT32, ""));
+    file.mut_message_type().push(cream);
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"directive @oneOf on INPUT_OBJECT
+
+type Pizza {
+  name: String!
+  tomatoSauce: String
+  cream: Cream
+  base: PizzaBaseCase
+}
 
-`"##)
+enum PizzaBaseCase {
+  TOMATO_SAUCE
+  CREAM
+}
+
+input PizzaInput {
+  name: String
+  base: PizzaBaseInput
+}
+
+input PizzaBaseInput @oneOf {
+  tomatoSauce: String
+  cream: CreamInput
+}
+
+type Cream {
+  fat: Int!
+}
+
+input CreamInput {
+  fat: Int
+}"##,
+        )
+        .unwrap();
+
+    // The resolvers tell which member is set, and merge the input one into the message.
+    let project = Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto");
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains(concat!(
+        "    tomatoSauce: message.tomato_sauce,\n",
+        "    cream: fromGrpc.Cream(message.cream),\n",
+        "    base: message.tomato_sauce != null ? 'TOMATO_SAUCE' : message.cream != null ? 'CREAM' : null,\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "  Pizza: input => input == null ? null : ({\n",
+        "    name: input.name,\n",
+        "    ...toGrpc.PizzaBase(input.base),\n",
+        "  }),\n",
+        "  PizzaBase: input => input == null ? null : ({\n",
+        "    tomato_sauce: input.tomatoSauce,\n",
+        "    cream: toGrpc.Cream(input.cream),\n",
+        "  }),\n",
+    )));
+    assert!(!resolvers.contains("__resolveType"));
+
+    let resolvers = project.generate("outputs=resolvers,types=all,oneof=union");
+    assert!(resolvers.contains(concat!(
+        "    base: message.tomato_sauce != null ? ",
+        "{ __typename: 'PizzaBaseTomatoSauce', tomatoSauce: message.tomato_sauce } : ",
+        "message.cream != null ? ",
+        "{ __typename: 'PizzaBaseCream', cream: fromGrpc.Cream(message.cream) } : null,\n",
+    )));
+    assert!(resolvers.contains("    ...toGrpc.PizzaBase(input.base),\n"));
+    assert!(
+        resolvers.contains("  PizzaBase: {\n    __resolveType: value => value.__typename,\n  },\n")
+    );
+
+    Project::new()
+        .source_file(file)
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all,oneof=union")
+        .expect(
+            "pizza.proto.out",
+            r##"directive @oneOf on INPUT_OBJECT
+
+type Pizza {
+  name: String!
+  base: PizzaBase
+}
+
+union PizzaBase = PizzaBaseTomatoSauce | PizzaBaseCream
+
+type PizzaBaseTomatoSauce {
+  tomatoSauce: String!
+}
+
+type PizzaBaseCream {
+  cream: Cream!
+}
+
+input PizzaInput {
+  name: String
+  base: PizzaBaseInput
+}
+
+input PizzaBaseInput @oneOf {
+  tomatoSauce: String
+  cream: CreamInput
+}
+
+type Cream {
+  fat: Int!
+}
+
+input CreamInput {
+  fat: Int
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn map_fields() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    let mut entry = DescriptorProto::new();
+    entry.set_name("ToppingCountsEntry".into());
+    entry.mut_options().set_map_entry(true);
+    entry.set_field(RepeatedField::from_vec(vec![
+        field("key", 1, TYPE_STRING, ""),
+        field("value", 2, TYPE_INT64, ""),
+    ]));
+    pizza.mut_nested_type().push(entry);
+    let mut topping_counts = field(
+        "topping_counts",
+        1,
+        TYPE_MESSAGE,
+        ".Pizza.ToppingCountsEntry",
+    );
+    topping_counts.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    pizza.mut_field().push(topping_counts);
+    file.mut_message_type().push(pizza);
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"scalar Int64
+
+type Pizza {
+  toppingCounts: [PizzaToppingCountsEntry!]!
+}
+
+type PizzaToppingCountsEntry {
+  key: String!
+  value: Int64!
+}
+
+input PizzaInput {
+  toppingCounts: [PizzaToppingCountsEntryInput!]
+}
+
+input PizzaToppingCountsEntryInput {
+  key: String!
+  value: Int64!
+}"##,
+        )
+        .unwrap();
+
+    Project::new()
+        .source_file(file)
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all,map=json")
+        .expect(
+            "pizza.proto.out",
+            r##"scalar JSON
+
+type Pizza {
+  toppingCounts: JSON!
+}
+
+input PizzaInput {
+  toppingCounts: JSON
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn map_fields_are_converted_in_resolvers() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut topping = DescriptorProto::new();
+    topping.set_name("Topping".into());
+    topping.mut_field().push(field("name", 1, TYPE_STRING, ""));
+    file.mut_message_type().push(topping);
+    let mut oven = DescriptorProto::new();
+    oven.set_name("Oven".into());
+    for &(name, key_type, value_type, value_type_name) in [
+        ("Counts", TYPE_STRING, TYPE_INT64, ""),
+        ("Toppings", TYPE_STRING, TYPE_MESSAGE, ".Topping"),
+        (
+            "Baked",
+            TYPE_INT32,
+            TYPE_MESSAGE,
+            ".google.protobuf.Timestamp",
+        ),
+    ]
+    .iter()
+    {
+        let mut entry = DescriptorProto::new();
+        entry.set_name(format!("{}Entry", name));
+        entry.mut_options().set_map_entry(true);
+        entry.set_field(RepeatedField::from_vec(vec![
+            field("key", 1, key_type, ""),
+            field("value", 2, value_type, value_type_name),
+        ]));
+        oven.mut_nested_type().push(entry);
+        let number = oven.get_field().len() as i32 + 1;
+        let type_name = format!(".Oven.{}Entry", name);
+        let mut map = field(&name.to_lowercase(), number, TYPE_MESSAGE, &type_name);
+        map.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+        oven.mut_field().push(map);
+    }
+    file.mut_message_type().push(oven);
+    let project = Project::new().source_file(file).target_file("oven.proto");
+
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains("const maps = {\n"));
+    assert!(resolvers.contains(concat!(
+        "    counts: maps.toEntries(message.counts, value => value, value => value),\n",
+        "    toppings: maps.toEntries(message.toppings, value => value, fromGrpc.Topping),\n",
+        "    baked: maps.toEntries(message.baked, value => value, wellKnownTypes.fromTimestamp),\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "    counts: maps.fromEntries(input.counts, value => value),\n",
+        "    toppings: maps.fromEntries(input.toppings, toGrpc.Topping),\n",
+        "    baked: maps.fromEntries(input.baked, wellKnownTypes.toTimestamp),\n",
+    )));
+
+    let resolvers = project.generate("outputs=resolvers,types=all,map=json");
+    assert!(resolvers.contains(concat!(
+        "    counts: maps.toObject(message.counts ?? {}, value => value),\n",
+        "    toppings: maps.toObject(message.toppings ?? {}, fromGrpc.Topping),\n",
+        "    baked: maps.toObject(message.baked ?? {}, wellKnownTypes.fromTimestamp),\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "    counts: maps.toObject(input.counts, value => value),\n",
+        "    toppings: maps.toObject(input.toppings, toGrpc.Topping),\n",
+        "    baked: maps.toObject(input.baked, wellKnownTypes.toTimestamp),\n",
+    )));
+}
+
+#[test]
+fn nullability_follows_field_presence() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    // `optional int32 slices = 2;` lives in a synthetic oneof.
+    let mut slices = field("slices", 2, TYPE_INT32, "");
+    slices.set_oneof_index(0);
+    slices.mut_unknown_fields().add_varint(17, 1);
+    let mut synthetic = OneofDescriptorProto::new();
+    synthetic.set_name("_slices".into());
+    pizza.mut_oneof_decl().push(synthetic);
+    let mut tags = field("tags", 4, TYPE_STRING, "");
+    tags.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    let mut toppings = field("toppings", 5, TYPE_MESSAGE, ".Topping");
+    toppings.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("name", 1, TYPE_STRING, ""),
+        slices,
+        field("topping", 3, TYPE_MESSAGE, ".Topping"),
+        tags,
+        toppings,
+    ]));
+    file.mut_message_type().push(pizza);
+    let mut topping = DescriptorProto::new();
+    topping.set_name("Topping".into());
+    file.mut_message_type().push(topping);
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"type Pizza {
+  name: String!
+  slices: Int
+  topping: Topping
+  tags: [String!]!
+  toppings: [Topping!]!
+}
+
+input PizzaInput {
+  name: String
+  slices: Int
+  topping: ToppingInput
+  tags: [String!]
+  toppings: [ToppingInput!]
+}
+
+type Topping {
+}
+
+input ToppingInput {
+}"##,
+        )
         .unwrap();
+
+    // grpc leaves unset fields out, which read as null when they have presence, and as their
+    // zero value otherwise.
+    let project = Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto");
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains(concat!(
+        "    name: message.name ?? '',\n",
+        "    slices: message.slices,\n",
+        "    topping: fromGrpc.Topping(message.topping),\n",
+        "    tags: message.tags || [],\n",
+        "    toppings: (message.toppings || []).map(fromGrpc.Topping),\n",
+    )));
+
+    file.set_syntax("proto2".into());
+    let project = Project::new().source_file(file).target_file("pizza.proto");
+    let resolvers = project.generate("outputs=resolvers,types=all");
+    assert!(resolvers.contains("    name: message.name,\n"));
+}
+
+#[test]
+fn response_supports_proto3_optional() {
+    let response = gen_response(&CodeGeneratorRequest::new());
+    assert_eq!(
+        support::unknown_varint(response.get_unknown_fields(), 2),
+        Some(1)
+    );
+}
+
+#[test]
+fn multiple_files() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut common = FileDescriptorProto::new();
+    common.set_name("common.proto".into());
+    common.set_package("common".into());
+    common.set_syntax("proto3".into());
+    let mut cheese = DescriptorProto::new();
+    cheese.set_name("Cheese".into());
+    cheese.mut_field().push(field("name", 1, TYPE_STRING, ""));
+    common.mut_message_type().push(cheese);
+    let mut unused = DescriptorProto::new();
+    unused.set_name("Unused".into());
+    common.mut_message_type().push(unused);
+
+    let mut pizza_file = FileDescriptorProto::new();
+    pizza_file.set_name("pizza.proto".into());
+    pizza_file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza
+        .mut_field()
+        .push(field("cheese", 1, TYPE_MESSAGE, ".common.Cheese"));
+    pizza_file.mut_message_type().push(pizza);
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut bake = MethodDescriptorProto::new();
+    bake.set_name("Bake".into());
+    bake.set_input_type(".Pizza".into());
+    bake.set_output_type(".Pizza".into());
+    oven.mut_method().push(bake);
+    pizza_file.mut_service().push(oven);
+
+    let mut menu_file = FileDescriptorProto::new();
+    menu_file.set_name("menu.proto".into());
+    menu_file.set_syntax("proto3".into());
+    let mut menu = DescriptorProto::new();
+    menu.set_name("Menu".into());
+    let mut pizzas = field("pizzas", 1, TYPE_MESSAGE, ".Pizza");
+    pizzas.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    menu.mut_field().push(pizzas);
+    menu_file.mut_message_type().push(menu);
+
+    let project = || {
+        Project::new()
+            .source_file(common.clone())
+            .source_file(pizza_file.clone())
+            .source_file(menu_file.clone())
+            .target_file("pizza.proto")
+            .target_file("menu.proto")
+    };
+
+    project()
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"type Pizza {
+  cheese: CommonCheese
+}
+
+input PizzaInput {
+  cheese: CommonCheeseInput
+}
+
+type OvenServiceMutations {
+  bake(pizza: PizzaInput!): Pizza!
+}
+
+type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}
+
+type Mutation {
+  oven: OvenServiceMutations!
+}"##,
+        )
+        .expect(
+            "menu.proto.out",
+            r##"type Menu {
+  pizzas: [Pizza!]!
+}
+
+input MenuInput {
+  pizzas: [PizzaInput!]
+}"##,
+        )
+        .unwrap();
+
+    project()
+        .parameter("outputs=schema,types=all,emit=merged")
+        .expect(
+            "schema.out",
+            r##"type Pizza {
+  cheese: CommonCheese
+}
+
+input PizzaInput {
+  cheese: CommonCheeseInput
+}
+
+type Menu {
+  pizzas: [Pizza!]!
+}
+
+input MenuInput {
+  pizzas: [PizzaInput!]
+}
+
+type CommonCheese {
+  name: String!
+}
+
+input CommonCheeseInput {
+  name: String
+}
+
+type OvenServiceMutations {
+  bake(pizza: PizzaInput!): Pizza!
+}
+
+type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}
+
+type Mutation {
+  oven: OvenServiceMutations!
+}"##,
+        )
+        .unwrap();
+
+    // The imported types are not part of the per-file schema, but the resolvers still convert them.
+    let config = Config::from_parameter("outputs=resolvers").unwrap();
+    let project = project();
+    let generated = gen(
+        &project.file_descriptors,
+        &project.files_to_generate,
+        &config,
+    )
+    .unwrap();
+    let resolvers = String::from_utf8(generated[0].content.clone()).unwrap();
+    assert_eq!(generated[0].name, "pizza.proto-resolvers.js");
+    assert!(resolvers.contains("  CommonCheese: message => "));
+    assert!(!resolvers.contains("Unused"));
+}
+
+#[test]
+fn methods_are_queries_or_mutations() {
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    // The option number given by `operation_option`, then `idempotency_level`, then the prefix.
+    let methods = [
+        ("GetTemperature", None, None),
+        ("Getaway", None, None),
+        ("ListPizzas", None, Some(2)),
+        ("Peek", None, Some(1)),
+        ("Preheat", Some(1), Some(2)),
+        ("GetDirty", Some(2), None),
+    ];
+    for &(name, operation, idempotency_level) in methods.iter() {
+        let mut method = MethodDescriptorProto::new();
+        method.set_name(name.into());
+        method.set_input_type(".google.protobuf.Empty".into());
+        method.set_output_type(".google.protobuf.Empty".into());
+        if let Some(operation) = operation {
+            method
+                .mut_options()
+                .mut_unknown_fields()
+                .add_varint(50001, operation);
+        }
+        if let Some(level) = idempotency_level {
+            method
+                .mut_options()
+                .mut_unknown_fields()
+                .add_varint(34, level);
+        }
+        oven.mut_method().push(method);
+    }
+    file.mut_service().push(oven);
+
+    Project::new()
+        .source_file(file)
+        .target_file("oven.proto")
+        .parameter("outputs=schema,operation_option=50001")
+        .expect(
+            "oven.proto.out",
+            r##"type OvenService {
+  getTemperature: Boolean!
+  peek: Boolean!
+  preheat: Boolean!
+}
+
+type OvenServiceMutations {
+  getaway: Boolean!
+  listPizzas: Boolean!
+  getDirty: Boolean!
+}
+
+type Query {
+  oven: OvenService!
+}
+
+type Mutation {
+  oven: OvenServiceMutations!
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn mutation_only_services_have_a_query() {
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut method = MethodDescriptorProto::new();
+    method.set_name("Preheat".into());
+    method.set_input_type(".google.protobuf.Empty".into());
+    method.set_output_type(".google.protobuf.Empty".into());
+    oven.mut_method().push(method);
+    file.mut_service().push(oven);
+
+    let project = Project::new().source_file(file).target_file("oven.proto");
+
+    assert_eq!(
+        project.generate("outputs=schema"),
+        r##"type OvenServiceMutations {
+  preheat: Boolean!
+}
+
+type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}
+
+type Mutation {
+  oven: OvenServiceMutations!
+}"##
+    );
+
+    let type_defs = project.generate("outputs=type_defs");
+    assert!(type_defs.contains(concat!(
+        "const Query = `\ntype Query {\n",
+        "  \"\"\"There are no queries, but GraphQL requires a field.\"\"\"\n",
+        "  _empty: Boolean\n}\n`",
+    )));
+    assert!(type_defs.contains("  Query,\n  Mutation,\n]"));
+}
+
+#[test]
+fn graphql_options_are_honored() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    const NAME: u32 = 1;
+    const SKIP: u32 = 2;
+    const DESCRIPTION: u32 = 3;
+    const DEPRECATION_REASON: u32 = 4;
+    const NULLABILITY: u32 = 5;
+    const SCALAR: u32 = 6;
+    const OPERATION: u32 = 7;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+
+    let mut size = EnumDescriptorProto::new();
+    size.set_name("Size".into());
+    graphql_options(
+        size.mut_options().mut_unknown_fields(),
+        &[(NAME, "PizzaSize")],
+        &[],
+    );
+    for (number, name) in ["SIZE_SMALL", "SIZE_LARGE", "SIZE_SECRET"]
+        .iter()
+        .enumerate()
+    {
+        let mut value = EnumValueDescriptorProto::new();
+        value.set_name(name.to_string());
+        value.set_number(number as i32);
+        size.mut_value().push(value);
+    }
+    let values = size.mut_value();
+    graphql_options(
+        values[0].mut_options().mut_unknown_fields(),
+        &[(NAME, "SMALL")],
+        &[],
+    );
+    graphql_options(
+        values[1].mut_options().mut_unknown_fields(),
+        &[(DEPRECATION_REASON, "Too \"large\"")],
+        &[],
+    );
+    graphql_options(
+        values[2].mut_options().mut_unknown_fields(),
+        &[],
+        &[(SKIP, 1)],
+    );
+    file.mut_enum_type().push(size);
+
+    let mut sauce = DescriptorProto::new();
+    sauce.set_name("Sauce".into());
+    graphql_options(sauce.mut_options().mut_unknown_fields(), &[], &[(SKIP, 1)]);
+    file.mut_message_type().push(sauce);
+
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    graphql_options(
+        pizza.mut_options().mut_unknown_fields(),
+        &[(NAME, "Pie"), (DESCRIPTION, " A pie.")],
+        &[],
+    );
+    let mut name = field("name", 1, TYPE_STRING, "");
+    graphql_options(
+        name.mut_options().mut_unknown_fields(),
+        &[(NAME, "title")],
+        &[(NULLABILITY, 1)],
+    );
+    let mut sauce = field("sauce", 2, TYPE_MESSAGE, ".Sauce");
+    graphql_options(sauce.mut_options().mut_unknown_fields(), &[], &[(SKIP, 1)]);
+    let mut price = field("price", 3, TYPE_INT64, "");
+    graphql_options(
+        price.mut_options().mut_unknown_fields(),
+        &[(SCALAR, "Money")],
+        &[],
+    );
+    let mut crust = field("crust", 4, TYPE_STRING, "");
+    graphql_options(
+        crust.mut_options().mut_unknown_fields(),
+        &[(DEPRECATION_REASON, "Always thin")],
+        &[],
+    );
+    let mut topping = field("topping", 5, TYPE_MESSAGE, ".Pizza");
+    graphql_options(
+        topping.mut_options().mut_unknown_fields(),
+        &[],
+        &[(NULLABILITY, 2)],
+    );
+    pizza.set_field(RepeatedField::from_vec(vec![
+        name,
+        sauce,
+        price,
+        crust,
+        topping,
+        field("size", 6, TYPE_ENUM, ".Size"),
+    ]));
+    file.mut_message_type().push(pizza);
+
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    graphql_options(
+        oven.mut_options().mut_unknown_fields(),
+        &[(NAME, "Kitchen"), (DESCRIPTION, " Where pies are made.")],
+        &[],
+    );
+    let mut bake = MethodDescriptorProto::new();
+    bake.set_name("Bake".into());
+    bake.set_input_type(".Pizza".into());
+    bake.set_output_type(".Pizza".into());
+    graphql_options(
+        bake.mut_options().mut_unknown_fields(),
+        &[(NAME, "bakePie"), (DEPRECATION_REASON, "Buy one")],
+        &[(OPERATION, 1)],
+    );
+    oven.mut_method().push(bake);
+    let mut clean = MethodDescriptorProto::new();
+    clean.set_name("Clean".into());
+    clean.set_input_type(".Sauce".into());
+    clean.set_output_type(".Sauce".into());
+    graphql_options(clean.mut_options().mut_unknown_fields(), &[], &[(SKIP, 1)]);
+    oven.mut_method().push(clean);
+    file.mut_service().push(oven);
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto")
+        .parameter("outputs=schema")
+        .expect(
+            "pizza.proto.out",
+            r##"scalar Money
+
+enum PizzaSize {
+  SMALL
+  SIZE_LARGE @deprecated(reason: "Too \"large\"")
+}
+
+"""A pie."""
+type Pie {
+  title: String
+  price: Money!
+  crust: String! @deprecated(reason: "Always thin")
+  topping: Pie!
+  size: PizzaSize!
+}
+
+"""A pie."""
+input PieInput {
+  title: String
+  price: Money
+  crust: String @deprecated(reason: "Always thin")
+  topping: PieInput
+  size: PizzaSize
+}
+
+"""Where pies are made."""
+type KitchenService {
+  bakePie(pie: PieInput!): Pie! @deprecated(reason: "Buy one")
+}
+
+type Query {
+  """Where pies are made."""
+  kitchen: KitchenService!
+}"##,
+        )
+        .unwrap();
+
+    let project = |file: &FileDescriptorProto| {
+        Project::new()
+            .source_file(file.clone())
+            .target_file("pizza.proto")
+    };
+    let resolvers = project(&file).generate("outputs=resolvers");
+    assert!(resolvers.contains("const KitchenStub = new proto.Oven(process.env.OVEN_BACKEND_URL"));
+    assert!(resolvers.contains("      bakePie: ({ pie: req }) => {"));
+    assert!(resolvers.contains("    title: message.name,"));
+    assert!(resolvers.contains("    name: input.title,"));
+    assert!(resolvers
+        .contains("  PizzaSize: {\n    SMALL: 'SIZE_SMALL',\n    SIZE_LARGE: 'SIZE_LARGE',\n  },"));
+
+    // Skipped types can only be referenced by skipped fields and methods.
+    file.mut_message_type()[1].mut_field()[1].clear_options();
+    let err = project(&file)
+        .try_generate("outputs=resolvers")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "pizza.proto: Pizza.sauce: references Sauce, which is skipped with the graphql options"
+    );
+}
+
+#[test]
+fn deprecated_elements() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("menu.proto".into());
+    file.set_syntax("proto3".into());
+
+    let mut size = EnumDescriptorProto::new();
+    size.set_name("Size".into());
+    for (number, name) in ["SMALL", "HUGE"].iter().enumerate() {
+        let mut value = EnumValueDescriptorProto::new();
+        value.set_name(name.to_string());
+        value.set_number(number as i32);
+        size.mut_value().push(value);
+    }
+    size.mut_value()[1].mut_options().set_deprecated(true);
+    file.mut_enum_type().push(size);
+
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    let mut crust = field("crust", 1, TYPE_STRING, "");
+    crust.mut_options().set_deprecated(true);
+    let mut diameter = field("diameter", 2, TYPE_INT32, "");
+    diameter.mut_options().set_deprecated(true);
+    pizza.set_field(RepeatedField::from_vec(vec![crust, diameter]));
+    file.mut_message_type().push(pizza);
+
+    let mut menu = ServiceDescriptorProto::new();
+    menu.set_name("Menu".into());
+    let mut list = MethodDescriptorProto::new();
+    list.set_name("List".into());
+    list.set_input_type(".google.protobuf.Empty".into());
+    list.set_output_type(".Pizza".into());
+    list.mut_options().set_deprecated(true);
+    menu.mut_method().push(list);
+    file.mut_service().push(menu);
+
+    let mut crust_comment = SourceCodeInfo_Location::new();
+    crust_comment.set_path(vec![4, 0, 2, 0]);
+    crust_comment.set_trailing_comments(" Always thin.\n".into());
+    let mut list_comment = SourceCodeInfo_Location::new();
+    list_comment.set_path(vec![6, 0, 2, 0]);
+    list_comment.set_leading_comments(" Use the\n pizzeria service.\n".into());
+    file.mut_source_code_info()
+        .set_location(RepeatedField::from_vec(vec![crust_comment, list_comment]));
+
+    Project::new()
+        .source_file(file)
+        .target_file("menu.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "menu.proto.out",
+            r##"enum Size {
+  SMALL
+  HUGE @deprecated
+}
+
+type Pizza {
+  """Always thin."""
+  crust: String! @deprecated(reason: "Always thin.")
+  diameter: Int! @deprecated
+}
+
+input PizzaInput {
+  """Always thin."""
+  crust: String @deprecated(reason: "Always thin.")
+  diameter: Int @deprecated
+}
+
+type MenuService {
+  """
+  Use the
+  pizzeria service.
+  """
+  list: Pizza! @deprecated(reason: "Use the pizzeria service.")
+}
+
+type Query {
+  menu: MenuService!
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn comments_are_descriptions() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.set_field(RepeatedField::from_vec(vec![field(
+        "topping",
+        1,
+        TYPE_STRING,
+        "",
+    )]));
+    file.mut_message_type().push(pizza);
+
+    let mut pizza_comment = SourceCodeInfo_Location::new();
+    pizza_comment.set_path(vec![4, 0]);
+    pizza_comment
+        .set_leading_detached_comments(RepeatedField::from_vec(vec![" Menu items.\n".to_string()]));
+    pizza_comment.set_leading_comments(" A pizza.\n".into());
+    let mut topping_comment = SourceCodeInfo_Location::new();
+    topping_comment.set_path(vec![4, 0, 2, 0]);
+    topping_comment
+        .set_leading_comments(" The topping, as in:\n\n   \"\"\"${cheese}\"\"\"\n".into());
+    topping_comment.set_trailing_comments(" Defaults to \\cheese\\\n".into());
+    file.mut_source_code_info()
+        .set_location(RepeatedField::from_vec(vec![
+            pizza_comment,
+            topping_comment,
+        ]));
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all,comments=leading+detached")
+        .expect(
+            "pizza.proto.out",
+            r##""""
+Menu items.
+
+A pizza.
+"""
+type Pizza {
+  """
+  The topping, as in:
+
+    \"""${cheese}\"""
+  """
+  topping: String!
+}
+
+"""
+Menu items.
+
+A pizza.
+"""
+input PizzaInput {
+  """
+  The topping, as in:
+
+    \"""${cheese}\"""
+  """
+  topping: String
+}"##,
+        )
+        .unwrap();
+
+    let type_defs = Project::new()
+        .source_file(file)
+        .target_file("pizza.proto")
+        .generate("outputs=type_defs,types=all");
+    assert!(type_defs.contains("const Pizza = `\n\"\"\"A pizza.\"\"\"\ntype Pizza {\n  \"\"\"\n"));
+    assert!(type_defs.contains(
+        "    \\\\\"\"\"\\${cheese}\\\\\"\"\"\n\n  Defaults to \\\\cheese\\\\\n  \"\"\"\n"
+    ));
+}
+
+#[test]
+fn comments_follow_declaration_order() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+
+    let mut size = EnumDescriptorProto::new();
+    size.set_name("Size".into());
+    for (number, name) in ["SMALL", "LARGE"].iter().enumerate() {
+        let mut value = EnumValueDescriptorProto::new();
+        value.set_name(name.to_string());
+        value.set_number(number as i32);
+        size.mut_value().push(value);
+    }
+    let mut crust = DescriptorProto::new();
+    crust.set_name("Crust".into());
+    crust.set_field(RepeatedField::from_vec(vec![field(
+        "thickness",
+        3,
+        TYPE_INT32,
+        "",
+    )]));
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    // Numbers that are neither sequential nor in declaration order.
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("topping", 7, TYPE_STRING, ""),
+        field("price", 2, TYPE_INT32, ""),
+    ]));
+    pizza.mut_enum_type().push(size);
+    pizza.mut_nested_type().push(crust);
+    file.mut_message_type().push(pizza);
+
+    let comment = |path: Vec<i32>, comment: &str| {
+        let mut location = SourceCodeInfo_Location::new();
+        location.set_path(path);
+        location.set_leading_comments(comment.to_string());
+        location
+    };
+    file.mut_source_code_info()
+        .set_location(RepeatedField::from_vec(vec![
+            comment(vec![4, 0, 2, 0], " Numbered 7."),
+            comment(vec![4, 0, 2, 1], " Numbered 2."),
+            comment(vec![4, 0, 3, 0, 2, 0], " Numbered 3."),
+            comment(vec![4, 0, 4, 0, 2, 0], " Small."),
+            comment(vec![4, 0, 4, 0, 2, 1], " Large."),
+            // The name of the LARGE value, which protoc records after the value itself.
+            comment(vec![4, 0, 4, 0, 2, 1, 1], " Not a description."),
+        ]));
+
+    Project::new()
+        .source_file(file)
+        .target_file("pizza.proto")
+        .parameter("outputs=schema,types=all")
+        .expect(
+            "pizza.proto.out",
+            r##"enum PizzaSize {
+  """Small."""
+  SMALL
+  """Large."""
+  LARGE
+}
+
+type Pizza {
+  """Numbered 7."""
+  topping: String!
+  """Numbered 2."""
+  price: Int!
+}
+
+input PizzaInput {
+  """Numbered 7."""
+  topping: String
+  """Numbered 2."""
+  price: Int
+}
+
+type PizzaCrust {
+  """Numbered 3."""
+  thickness: Int!
+}
+
+input PizzaCrustInput {
+  """Numbered 3."""
+  thickness: Int
+}"##,
+        )
+        .unwrap();
+}
+
+#[test]
+fn field_names_follow_json_name() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_package("pizzeria".into());
+    file.set_syntax("proto3".into());
+    let mut request = DescriptorProto::new();
+    request.set_name("BakeRequest".into());
+    let mut baked_at = field("baked_at", 1, TYPE_STRING, "");
+    baked_at.set_json_name("bakeTime".into());
+    request.set_field(RepeatedField::from_vec(vec![
+        baked_at,
+        field("oven_temperature", 2, TYPE_INT32, ""),
+    ]));
+    file.mut_message_type().push(request);
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut bake = MethodDescriptorProto::new();
+    bake.set_name("Bake".into());
+    bake.set_input_type(".pizzeria.BakeRequest".into());
+    bake.set_output_type(".pizzeria.BakeRequest".into());
+    oven.mut_method().push(bake);
+    file.mut_service().push(oven);
+
+    let project = Project::new().source_file(file).target_file("oven.proto");
+
+    let schema = project.generate("outputs=schema");
+    assert!(schema
+        .contains("type PizzeriaBakeRequest {\n  bakeTime: String!\n  ovenTemperature: Int!\n}"));
+    assert!(schema
+        .contains("  bake(pizzeriaBakeRequest: PizzeriaBakeRequestInput!): PizzeriaBakeRequest!"));
+
+    // The resolvers hand the stubs the proto names.
+    let resolvers = project.generate("outputs=resolvers");
+    assert!(resolvers.contains("      bake: ({ pizzeriaBakeRequest: req }) => {"));
+    assert!(resolvers.contains("    bakeTime: message.baked_at ?? '',\n    ovenTemperature: message.oven_temperature ?? 0,"));
+    assert!(resolvers
+        .contains("    baked_at: input.bakeTime,\n    oven_temperature: input.ovenTemperature,"));
+
+    let schema = project.generate("outputs=schema,field_case=snake");
+    assert!(schema
+        .contains("type PizzeriaBakeRequest {\n  baked_at: String!\n  oven_temperature: Int!\n}"));
+    assert!(schema.contains("  bake(pizzeria_bake_request: PizzeriaBakeRequestInput!)"));
+}
+
+#[test]
+fn type_names_are_consistent() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_package("acme.pizza".into());
+    file.set_syntax("proto3".into());
+    let mut size = EnumDescriptorProto::new();
+    size.set_name("Size".into());
+    let mut small = EnumValueDescriptorProto::new();
+    small.set_name("SMALL".into());
+    size.mut_value().push(small);
+    let mut crust = DescriptorProto::new();
+    crust.set_name("Crust".into());
+    crust
+        .mut_field()
+        .push(field("size", 1, TYPE_ENUM, ".acme.pizza.Pizza.Size"));
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("size", 1, TYPE_ENUM, ".acme.pizza.Pizza.Size"),
+        field("crust", 2, TYPE_MESSAGE, ".acme.pizza.Pizza.Crust"),
+    ]));
+    pizza.mut_enum_type().push(size);
+    pizza.mut_nested_type().push(crust);
+    file.mut_message_type().push(pizza);
+
+    let mut menu_file = FileDescriptorProto::new();
+    menu_file.set_name("menu.proto".into());
+    menu_file.set_package("acme.menu".into());
+    menu_file.set_syntax("proto3".into());
+    let mut menu_pizza = DescriptorProto::new();
+    menu_pizza.set_name("Pizza".into());
+    menu_pizza
+        .mut_field()
+        .push(field("pizza", 1, TYPE_MESSAGE, ".acme.pizza.Pizza"));
+    menu_file.mut_message_type().push(menu_pizza);
+
+    let project = |files: &[&str]| {
+        files.iter().fold(
+            Project::new()
+                .source_file(file.clone())
+                .source_file(menu_file.clone()),
+            |project, name| project.target_file(name),
+        )
+    };
+
+    // Nested types are defined and referenced under the same names.
+    let schema = project(&["pizza.proto"]).generate("outputs=schema,types=all");
+    assert!(schema.contains("enum AcmePizzaPizzaSize {"));
+    assert!(schema.contains(
+        "type AcmePizzaPizza {\n  size: AcmePizzaPizzaSize!\n  crust: AcmePizzaPizzaCrust\n}"
+    ));
+    assert!(schema.contains("type AcmePizzaPizzaCrust {\n  size: AcmePizzaPizzaSize!\n}"));
+
+    let both = ["pizza.proto", "menu.proto"];
+    let schema = project(&both)
+        .generate("outputs=schema,types=all,emit=merged,type_naming=strip_common_prefix");
+    assert!(
+        schema.contains("type PizzaPizza {\n  size: PizzaPizzaSize!\n  crust: PizzaPizzaCrust\n}")
+    );
+    assert!(schema.contains("type MenuPizza {\n  pizza: PizzaPizza\n}"));
+
+    let schema = project(&["pizza.proto"]).generate("outputs=schema,types=all,type_naming=short");
+    assert!(schema.contains("type Pizza {\n  size: PizzaSize!\n  crust: PizzaCrust\n}"));
+
+    let err = project(&both)
+        .try_generate("outputs=schema,types=all,emit=merged,type_naming=short")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "menu.proto: acme.menu.Pizza: acme.pizza.Pizza and acme.menu.Pizza are both named Pizza in the \
+         GraphQL schema, rename one of them with the graphql options or use another type_naming"
+    );
+}
+
+#[test]
+fn generated_type_names_are_checked_for_collisions() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_package("pizza".into());
+    file.set_syntax("proto3".into());
+    let mut base = DescriptorProto::new();
+    base.set_name("Base".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    let mut crust = field("crust", 1, TYPE_STRING, "");
+    crust.set_oneof_index(0);
+    pizza.mut_field().push(crust);
+    let mut oneof = OneofDescriptorProto::new();
+    oneof.set_name("base".into());
+    pizza.mut_oneof_decl().push(oneof);
+    pizza.mut_nested_type().push(base);
+    file.mut_message_type().push(pizza);
+
+    let project = Project::new().source_file(file).target_file("pizza.proto");
+    let parameter = "outputs=schema,types=all,type_naming=short,oneof=union";
+    assert_eq!(
+        project.try_generate(parameter).err().unwrap().to_string(),
+        "pizza.proto: pizza.Pizza.Base: the oneof pizza.Pizza.base and pizza.Pizza.Base are both \
+         named PizzaBase in the GraphQL schema, rename one of them with the graphql options or \
+         use another type_naming"
+    );
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_package("pizza".into());
+    file.set_syntax("proto3".into());
+    for name in ["Pizza", "PizzaInput"].iter() {
+        let mut message = DescriptorProto::new();
+        message.set_name(name.to_string());
+        message.mut_field().push(field("title", 1, TYPE_STRING, ""));
+        file.mut_message_type().push(message);
+    }
+
+    let project = Project::new().source_file(file).target_file("pizza.proto");
+    assert_eq!(
+        project.try_generate("outputs=schema,types=all,type_naming=short").err().unwrap().to_string(),
+        "pizza.proto: pizza.PizzaInput: the input type of pizza.Pizza and pizza.PizzaInput are both \
+         named PizzaInput in the GraphQL schema, rename one of them with the graphql options or use \
+         another type_naming"
+    );
+}
+
+#[test]
+fn root_and_scalar_names_are_checked_for_collisions() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let file = |messages: Vec<DescriptorProto>| {
+        let mut file = FileDescriptorProto::new();
+        file.set_name("pizza.proto".into());
+        file.set_package("pizza".into());
+        file.set_syntax("proto3".into());
+        file.set_message_type(RepeatedField::from_vec(messages));
+        Project::new().source_file(file).target_file("pizza.proto")
+    };
+    let message = |name: &str, field: FieldDescriptorProto| {
+        let mut message = DescriptorProto::new();
+        message.set_name(name.into());
+        message.mut_field().push(field);
+        message
+    };
+    let parameter = "outputs=schema,types=all,type_naming=short";
+
+    let project = file(vec![message("Query", field("title", 1, TYPE_STRING, ""))]);
+    assert_eq!(
+        project.try_generate(parameter).err().unwrap().to_string(),
+        "pizza.proto: pizza.Query: the Query root type and pizza.Query are both named Query in \
+         the GraphQL schema, rename one of them with the graphql options or use another \
+         type_naming"
+    );
+
+    let project = file(vec![
+        message("DateTime", field("title", 1, TYPE_STRING, "")),
+        message(
+            "Pizza",
+            field("baked_at", 1, TYPE_MESSAGE, ".google.protobuf.Timestamp"),
+        ),
+    ]);
+    assert_eq!(
+        project.try_generate(parameter).err().unwrap().to_string(),
+        "pizza.proto: pizza.Pizza.baked_at: pizza.DateTime and the DateTime scalar are both \
+         named DateTime in the GraphQL schema, rename one of them with the graphql options or \
+         use another type_naming"
+    );
+}
+
+#[test]
+fn types_are_reachable_from_methods() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+    const SKIP: u32 = 2;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let mut size = EnumDescriptorProto::new();
+    size.set_name("Size".into());
+    let mut small = EnumValueDescriptorProto::new();
+    small.set_name("SMALL".into());
+    size.mut_value().push(small);
+    file.mut_enum_type().push(size);
+    let message = |name: &str, fields: Vec<FieldDescriptorProto>| {
+        let mut message = DescriptorProto::new();
+        message.set_name(name.into());
+        message.set_field(RepeatedField::from_vec(fields));
+        message
+    };
+    let mut audit = field("audit", 3, TYPE_MESSAGE, ".Audit");
+    graphql_options(audit.mut_options().mut_unknown_fields(), &[], &[(SKIP, 1)]);
+    file.set_message_type(RepeatedField::from_vec(vec![
+        message(
+            "BakeRequest",
+            vec![
+                field("pizza", 1, TYPE_MESSAGE, ".Pizza"),
+                field("size", 2, TYPE_ENUM, ".Size"),
+            ],
+        ),
+        message("Pizza", vec![field("name", 1, TYPE_STRING, "")]),
+        message(
+            "Receipt",
+            vec![
+                field("pizza", 1, TYPE_MESSAGE, ".Pizza"),
+                field("payment", 2, TYPE_MESSAGE, ".Payment"),
+                audit,
+            ],
+        ),
+        message("Payment", vec![field("amount", 1, TYPE_INT32, "")]),
+        message("Audit", vec![field("clerk", 1, TYPE_STRING, "")]),
+    ]));
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut bake = MethodDescriptorProto::new();
+    bake.set_name("Bake".into());
+    bake.set_input_type(".BakeRequest".into());
+    bake.set_output_type(".Receipt".into());
+    oven.mut_method().push(bake);
+    file.mut_service().push(oven);
+
+    Project::new()
+        .source_file(file.clone())
+        .target_file("oven.proto")
+        .parameter("outputs=schema")
+        .expect(
+            "oven.proto.out",
+            r##"enum Size {
+  SMALL
+}
+
+input BakeRequestInput {
+  pizza: PizzaInput
+  size: Size
+}
+
+type Pizza {
+  name: String!
+}
+
+input PizzaInput {
+  name: String
+}
+
+type Receipt {
+  pizza: Pizza
+  payment: Payment
+}
+
+type Payment {
+  amount: Int!
+}
+
+type OvenServiceMutations {
+  bake(bakeRequest: BakeRequestInput!): Receipt!
+}
+
+type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}
+
+type Mutation {
+  oven: OvenServiceMutations!
+}"##,
+        )
+        .unwrap();
+
+    let project = Project::new().source_file(file).target_file("oven.proto");
+    let type_defs = project.generate("outputs=type_defs");
+    assert!(type_defs
+        .contains("  BakeRequestInput,\n  Pizza,\n  PizzaInput,\n  Receipt,\n  Payment,\n"));
+    assert!(!type_defs.contains("Audit"));
+
+    let schema = project.generate("outputs=schema,types=all");
+    assert!(schema.contains("type BakeRequest {"));
+    assert!(schema.contains("input PaymentInput {"));
+    assert!(schema.contains("type Audit {"));
+}
+
+#[test]
+fn enum_prefixes_and_unspecified_values() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizza.proto".into());
+    file.set_syntax("proto3".into());
+    let mut fat_type = EnumDescriptorProto::new();
+    fat_type.set_name("FatType".into());
+    for (number, name) in [
+        "FAT_TYPE_UNSPECIFIED",
+        "FAT_TYPE_OLIVE_OIL",
+        "FAT_TYPE_LARD",
+    ]
+    .iter()
+    .enumerate()
+    {
+        let mut value = EnumValueDescriptorProto::new();
+        value.set_name(name.to_string());
+        value.set_number(number as i32);
+        fat_type.mut_value().push(value);
+    }
+    file.mut_enum_type().push(fat_type);
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    let mut fats = field("fats", 3, TYPE_ENUM, ".FatType");
+    fats.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    let mut entry = DescriptorProto::new();
+    entry.set_name("FatByCrustEntry".into());
+    entry.mut_options().set_map_entry(true);
+    entry.set_field(RepeatedField::from_vec(vec![
+        field("key", 1, TYPE_STRING, ""),
+        field("value", 2, TYPE_ENUM, ".FatType"),
+    ]));
+    pizza.mut_nested_type().push(entry);
+    let mut fat_by_crust = field("fat_by_crust", 4, TYPE_MESSAGE, ".Pizza.FatByCrustEntry");
+    fat_by_crust.set_label(FieldDescriptorProto_Label::LABEL_REPEATED);
+    pizza.set_field(RepeatedField::from_vec(vec![
+        field("fat", 1, TYPE_ENUM, ".FatType"),
+        field("name", 2, TYPE_STRING, ""),
+        fats,
+        fat_by_crust,
+    ]));
+    file.mut_message_type().push(pizza);
+    let mut menu = ServiceDescriptorProto::new();
+    menu.set_name("Menu".into());
+    let mut get_pizza = MethodDescriptorProto::new();
+    get_pizza.set_name("GetPizza".into());
+    get_pizza.set_input_type(".Pizza".into());
+    get_pizza.set_output_type(".Pizza".into());
+    menu.mut_method().push(get_pizza);
+    file.mut_service().push(menu);
+
+    let project = Project::new().source_file(file).target_file("pizza.proto");
+
+    let schema = project.generate("outputs=schema,enum_prefix=strip");
+    assert!(schema.contains("enum FatType {\n  UNSPECIFIED\n  OLIVE_OIL\n  LARD\n}"));
+    assert!(schema.contains("type Pizza {\n  fat: FatType!\n"));
+
+    let schema = project.generate("outputs=schema,enum_prefix=strip,unspecified=null");
+    assert!(schema.contains("enum FatType {\n  OLIVE_OIL\n  LARD\n}"));
+    assert!(schema.contains("type Pizza {\n  fat: FatType\n"));
+    assert!(schema.contains("  fats: [FatType!]!\n"));
+    assert!(schema.contains("type PizzaFatByCrustEntry {\n  key: String!\n  value: FatType\n}"));
+    assert!(
+        schema.contains("input PizzaFatByCrustEntryInput {\n  key: String!\n  value: FatType!\n}")
+    );
+
+    // Unset enums read as their zero value, unless it is left out.
+    let resolvers = project.generate("outputs=resolvers");
+    assert!(resolvers.contains("    fat: message.fat ?? 'FAT_TYPE_UNSPECIFIED',\n"));
+
+    // The resolvers map the values to the proto ones, and the zero value to null.
+    let resolvers = project.generate("outputs=resolvers,enum_prefix=strip,unspecified=null");
+    assert!(resolvers.contains(
+        "  FatType: {\n    OLIVE_OIL: 'FAT_TYPE_OLIVE_OIL',\n    LARD: 'FAT_TYPE_LARD',\n  },"
+    ));
+    assert!(resolvers.contains(
+        "    fat: (value => value === 'FAT_TYPE_UNSPECIFIED' ? null : value)(message.fat),"
+    ));
+    assert!(resolvers.contains("    fat: input.fat,"));
+    // Lists have no null items, so they leave the zero value out.
+    assert!(resolvers.contains(
+        "    fats: (message.fats || []).filter(value => value !== 'FAT_TYPE_UNSPECIFIED'),"
+    ));
+    assert!(resolvers.contains("    fats: input.fats,"));
+
+    let schema = project.generate("outputs=schema,unspecified=null");
+    assert!(schema.contains("enum FatType {\n  FAT_TYPE_OLIVE_OIL\n  FAT_TYPE_LARD\n}"));
+}
+
+#[test]
+fn server_streaming_methods_are_subscriptions() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizzeria.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.mut_field().push(field("title", 1, TYPE_STRING, ""));
+    file.mut_message_type().push(pizza);
+    let mut pizzeria = ServiceDescriptorProto::new();
+    pizzeria.set_name("Pizzeria".into());
+    for &(name, server_streaming) in [("GetPizza", false), ("ObservePizzas", true)].iter() {
+        let mut method = MethodDescriptorProto::new();
+        method.set_name(name.into());
+        method.set_input_type(".Pizza".into());
+        method.set_output_type(".Pizza".into());
+        method.set_server_streaming(server_streaming);
+        pizzeria.mut_method().push(method);
+    }
+    file.mut_service().push(pizzeria);
+
+    let project = Project::new()
+        .source_file(file)
+        .target_file("pizzeria.proto");
+
+    assert_eq!(
+        project.generate("outputs=schema"),
+        r##"type Pizza {
+  title: String!
+}
+
+input PizzaInput {
+  title: String
+}
+
+type PizzeriaService {
+  getPizza(pizza: PizzaInput!): Pizza!
+}
+
+type Query {
+  pizzeria: PizzeriaService!
+}
+
+type Subscription {
+  pizzeriaObservePizzas(pizza: PizzaInput!): Pizza!
+}"##
+    );
+
+    let type_defs = project.generate("outputs=type_defs");
+    assert!(type_defs.contains("const Subscription = `\ntype Subscription {\n"));
+    assert!(type_defs.contains("  Query,\n  Subscription,\n]"));
+
+    let resolvers = project.generate("outputs=resolvers");
+    assert!(resolvers.contains("    pizzeria: () => ({\n      getPizza: ({ pizza: req }) => {"));
+    assert!(!resolvers.contains("observePizzas"));
+    assert!(resolvers.contains(concat!(
+        "  Subscription: {\n    pizzeriaObservePizzas: {\n",
+        "      subscribe: (parent, { pizza: req }) => {\n",
+        "        const call = PizzeriaStub.ObservePizzas(toGrpc.Pizza(req))\n",
+        "        return subscribeTo(call, data => fromGrpc.Pizza(data))\n",
+        "      },\n",
+        "      resolve: resolveResponse,\n",
+    )));
+    assert!(resolvers.contains("const subscribeTo = (call, convert) => {"));
+    assert!(!resolvers.contains("pubsub"));
+    // A failed call gives its error as the last value, which resolveResponse throws.
+    assert!(resolvers.contains(concat!(
+        "  call.on('error', err => {\n",
+        "    if (finished) return\n",
+        "    push(err)\n",
+        "    finish()\n",
+        "  })\n",
+    )));
+    assert!(resolvers.contains("  if (response instanceof Error) throw response\n"));
+    // The end of the call ends the subscription.
+    assert!(resolvers.contains("  call.on('end', finish)\n"));
+    // Ending the subscription early cancels the call.
+    assert!(resolvers.contains(concat!(
+        "  const stop = () => {\n",
+        "    if (!finished) {\n",
+        "      responses.length = 0\n",
+        "      finish()\n",
+        "      call.cancel()\n",
+        "    }\n",
+    )));
+    assert!(resolvers.contains("    return: stop,\n    throw: err => stop().then("));
+}
+
+#[test]
+fn client_streaming_methods_take_lists() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("pizzeria.proto".into());
+    file.set_syntax("proto3".into());
+    let mut pizza = DescriptorProto::new();
+    pizza.set_name("Pizza".into());
+    pizza.mut_field().push(field("title", 1, TYPE_STRING, ""));
+    file.mut_message_type().push(pizza);
+    let mut pizzeria = ServiceDescriptorProto::new();
+    pizzeria.set_name("Pizzeria".into());
+    for &(name, server_streaming) in [("GetPizzas", false), ("SwapPizzas", true)].iter() {
+        let mut method = MethodDescriptorProto::new();
+        method.set_name(name.into());
+        method.set_input_type(".Pizza".into());
+        method.set_output_type(".Pizza".into());
+        method.set_client_streaming(true);
+        method.set_server_streaming(server_streaming);
+        pizzeria.mut_method().push(method);
+    }
+    file.mut_service().push(pizzeria);
+
+    let project = Project::new()
+        .source_file(file)
+        .target_file("pizzeria.proto");
+
+    assert_eq!(
+        project.generate("outputs=schema"),
+        r##"type Pizza {
+  title: String!
+}
+
+input PizzaInput {
+  title: String
+}
+
+type PizzeriaServiceMutations {
+  getPizzas(pizza: [PizzaInput!]!): Pizza!
+}
+
+type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}
+
+type Mutation {
+  pizzeria: PizzeriaServiceMutations!
+}
+
+type Subscription {
+  pizzeriaSwapPizzas(pizza: [PizzaInput!]!): Pizza!
+}"##
+    );
+
+    let resolvers = project.generate("outputs=resolvers");
+    assert!(resolvers.contains(concat!(
+        "      getPizzas: ({ pizza: req }) => {\n",
+        "        return new Promise((resolve, reject) => {\n",
+        "          const call = PizzeriaStub.GetPizzas((err, res) => err ? reject(err) : ",
+        "resolve(fromGrpc.Pizza(res)))\n",
+        "          for (const message of req && req.map(toGrpc.Pizza) || []) call.write(message)\n",
+        "          call.end()\n",
+        "        })\n",
+    )));
+    assert!(resolvers.contains(concat!(
+        "        const call = PizzeriaStub.SwapPizzas()\n",
+        "        for (const message of req && req.map(toGrpc.Pizza) || []) call.write(message)\n",
+    )));
+}
+
+#[test]
+fn services_are_loaded_with_the_configured_grpc() {
+    let mut file = FileDescriptorProto::new();
+    file.set_name("acme/oven.proto".into());
+    file.set_package("acme.kitchen".into());
+    file.set_syntax("proto3".into());
+    let mut oven = ServiceDescriptorProto::new();
+    oven.set_name("Oven".into());
+    let mut method = MethodDescriptorProto::new();
+    method.set_name("Bake".into());
+    method.set_input_type(".google.protobuf.Empty".into());
+    method.set_output_type(".google.protobuf.Empty".into());
+    oven.mut_method().push(method);
+    file.mut_service().push(oven);
+
+    let project = Project::new()
+        .source_file(file)
+        .target_file("acme/oven.proto");
+
+    let resolvers = project.generate("outputs=resolvers,include_paths=proto+vendor");
+    assert!(resolvers.starts_with(
+        "const grpc = require('@grpc/grpc-js')\nconst protoLoader = require('@grpc/proto-loader')\n"
+    ));
+    assert!(resolvers.contains(
+        r##"const proto = grpc.loadPackageDefinition(protoLoader.loadSync([
+  'acme/oven.proto',
+], {
+  keepCase: true,
+  longs: String,
+  enums: String,
+  defaults: false,
+  oneofs: true,
+  includeDirs: ['proto', 'vendor'],
+}))
+
+const OvenStub = new proto.acme.kitchen.Oven(process.env.OVEN_BACKEND_URL, "##
+    ));
+
+    let resolvers = project.generate("outputs=resolvers,grpc=legacy");
+    assert!(resolvers.starts_with("const grpc = require('grpc')\n"));
+    assert!(resolvers.contains("const AcmeOven = grpc.load('./acme/oven.proto')\n"));
+    assert!(resolvers.contains("const OvenStub = new AcmeOven.Oven(process.env.OVEN_BACKEND_URL, "));
+
+    // File names and include paths are escaped in the JS strings.
+    let resolvers = project.generate(r"outputs=resolvers,include_paths=C:\protos+Bob's");
+    assert!(resolvers.contains(r"  includeDirs: ['C:\\protos', 'Bob\'s'],"));
+}
+
+#[test]
+fn legacy_grpc_refuses_enums_and_oneofs() {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    let file = |fields: Vec<FieldDescriptorProto>| {
+        let mut file = FileDescriptorProto::new();
+        file.set_name("oven.proto".into());
+        file.set_syntax("proto3".into());
+        let mut size = EnumDescriptorProto::new();
+        size.set_name("Size".into());
+        let mut small = EnumValueDescriptorProto::new();
+        small.set_name("SMALL".into());
+        size.mut_value().push(small);
+        file.mut_enum_type().push(size);
+        let mut pizza = DescriptorProto::new();
+        pizza.set_name("Pizza".into());
+        pizza.set_field(RepeatedField::from_vec(fields));
+        let mut topping = OneofDescriptorProto::new();
+        topping.set_name("topping".into());
+        pizza.mut_oneof_decl().push(topping);
+        file.mut_message_type().push(pizza);
+        let mut oven = ServiceDescriptorProto::new();
+        oven.set_name("Oven".into());
+        let mut method = MethodDescriptorProto::new();
+        method.set_name("Bake".into());
+        method.set_input_type(".Pizza".into());
+        method.set_output_type(".Pizza".into());
+        oven.mut_method().push(method);
+        file.mut_service().push(oven);
+        Project::new().source_file(file).target_file("oven.proto")
+    };
+    let mut cheese = field("cheese", 2, TYPE_STRING, "");
+    cheese.set_oneof_index(0);
+
+    let project = file(vec![field("title", 1, TYPE_STRING, "")]);
+    let resolvers = project.generate("outputs=resolvers,grpc=legacy");
+    assert!(resolvers.contains("const Oven = grpc.load('./oven.proto')\n"));
+    assert!(resolvers.contains("    title: message.title ?? '',\n"));
+    assert!(resolvers.contains(concat!(
+        "        return new Promise((resolve, reject) => OvenStub.Bake(toGrpc.Pizza(req), ",
+        "(err, res) => err ? reject(err) : resolve(fromGrpc.Pizza(res))))\n",
+    )));
+
+    let project = file(vec![field("size", 1, TYPE_ENUM, ".Size")]);
+    assert_eq!(
+        project
+            .try_generate("outputs=resolvers,grpc=legacy")
+            .err()
+            .unwrap()
+            .to_string(),
+        "oven.proto: Pizza.size: the grpc=legacy loader reads enum values as numbers, which the \
+         resolvers cannot convert, use grpc=grpc_js or skip the field with the graphql options"
+    );
+    // The schema does not depend on the grpc library.
+    project.generate("outputs=schema,grpc=legacy");
+
+    let project = file(vec![cheese]);
+    assert_eq!(
+        project
+            .try_generate("outputs=resolvers,grpc=legacy")
+            .err()
+            .unwrap()
+            .to_string(),
+        "oven.proto: Pizza.cheese: the grpc=legacy loader does not tell which member of a oneof \
+         is set, which the resolvers cannot convert, use grpc=grpc_js or skip the field with the \
+         graphql options"
+    );
+}
+
+#[test]
+fn root_fields_can_be_flat() {
+    const NAME: u32 = 1;
+
+    let mut file = FileDescriptorProto::new();
+    file.set_name("oven.proto".into());
+    file.set_syntax("proto3".into());
+    let service = |name: &str, methods: &[&str]| {
+        let mut service = ServiceDescriptorProto::new();
+        service.set_name(name.into());
+        for &name in methods {
+            let mut method = MethodDescriptorProto::new();
+            method.set_name(name.into());
+            method.set_input_type(".google.protobuf.Empty".into());
+            method.set_output_type(".google.protobuf.Empty".into());
+            service.mut_method().push(method);
+        }
+        service
+    };
+    file.mut_service()
+        .push(service("Oven", &["GetTemperature", "Bake"]));
+    file.mut_service().push(service("Kitchen", &["Clean"]));
+
+    let project = |file: &FileDescriptorProto| {
+        Project::new()
+            .source_file(file.clone())
+            .target_file("oven.proto")
+    };
+
+    assert_eq!(
+        project(&file).generate("outputs=schema,root_fields=flat"),
+        r##"type Query {
+  ovenGetTemperature: Boolean!
+}
+
+type Mutation {
+  ovenBake: Boolean!
+  kitchenClean: Boolean!
+}"##
+    );
+
+    let resolvers = project(&file).generate("outputs=resolvers,root_fields=flat");
+    assert!(resolvers.contains(concat!(
+        "  Mutation: {\n",
+        "    ovenBake: (parent, _) => {\n",
+        "      return new Promise((resolve, reject) => OvenStub.Bake({}, ",
+        "(err, res) => err ? reject(err) : resolve(wellKnownTypes.fromEmpty(res))))\n",
+        "    },\n",
+        "    kitchenClean: (parent, _) => {\n",
+    )));
+
+    // Methods renamed with the options are named as they say, and must not collide.
+    let clean = &mut file.mut_service()[1].mut_method()[0];
+    graphql_options(
+        clean.mut_options().mut_unknown_fields(),
+        &[(NAME, "ovenBake")],
+        &[],
+    );
+    assert_eq!(
+        project(&file)
+            .try_generate("outputs=schema,root_fields=flat")
+            .err()
+            .unwrap()
+            .to_string(),
+        "oven.proto: Kitchen.Clean: Oven.Bake and Kitchen.Clean are both the Mutation.ovenBake \
+         field in the GraphQL schema, rename one of them with the graphql options"
+    );
+    assert!(project(&file).try_generate("outputs=schema").is_ok());
 }
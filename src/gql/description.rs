@@ -0,0 +1,135 @@
+use config::Comments;
+use protobuf::descriptor::SourceCodeInfo_Location;
+
+/// Removes the indentation the lines of a comment share, and the blank lines around it.
+pub fn normalize(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let indentation = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                ""
+            } else {
+                &line[indentation..]
+            }
+        })
+        .skip_while(|line| line.is_empty())
+        .collect();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |idx| idx + 1);
+
+    lines[..end].join("\n")
+}
+
+/// The description of a proto element, made of the comments of its location the config keeps,
+/// each as a paragraph of its own.
+pub fn from_comments(location: &SourceCodeInfo_Location, comments: &Comments) -> String {
+    let mut paragraphs: Vec<String> = Vec::new();
+    if comments.detached {
+        paragraphs.extend(
+            location
+                .get_leading_detached_comments()
+                .iter()
+                .map(|comment| normalize(comment)),
+        );
+    }
+    if comments.leading {
+        paragraphs.push(normalize(location.get_leading_comments()));
+    }
+    if comments.trailing {
+        paragraphs.push(normalize(location.get_trailing_comments()));
+    }
+
+    paragraphs.retain(|paragraph| !paragraph.is_empty());
+    paragraphs.join("\n\n")
+}
+
+/// Writes a description as a block string on the lines before the element it describes, which is
+/// indented with `indent`. Empty descriptions are left out.
+pub fn write_description(
+    formatter: &mut ::std::fmt::Formatter,
+    description: &str,
+    indent: &str,
+) -> Result<(), ::std::fmt::Error> {
+    let description = normalize(description);
+    if description.is_empty() {
+        return Ok(());
+    }
+
+    // Only triple quotes are escaped in block strings.
+    let escaped = description.replace(r#"""""#, r#"\""""#);
+    // A quote or a backslash right before the closing quotes would be read as part of them.
+    if !escaped.contains('\n') && !escaped.ends_with('"') && !escaped.ends_with('\\') {
+        return writeln!(formatter, r#"{}"""{}""""#, indent, escaped);
+    }
+
+    writeln!(formatter, r#"{}""""#, indent)?;
+    for line in escaped.lines() {
+        if line.is_empty() {
+            writeln!(formatter)?;
+        } else {
+            writeln!(formatter, "{}{}", indent, line)?;
+        }
+    }
+    writeln!(formatter, r#"{}""""#, indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Described(&'static str);
+
+    impl ::std::fmt::Display for Described {
+        fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+            write_description(formatter, self.0, "  ")
+        }
+    }
+
+    #[test]
+    fn descriptions_are_block_strings() {
+        assert_eq!(Described("").to_string(), "");
+        assert_eq!(
+            Described(" A pizza.\n").to_string(),
+            "  \"\"\"A pizza.\"\"\"\n"
+        );
+        assert_eq!(
+            Described("\n  Says \"\"\"hi\"\"\"\n\n    indented\n\n").to_string(),
+            "  \"\"\"\n  Says \\\"\"\"hi\\\"\"\"\n\n    indented\n  \"\"\"\n"
+        );
+        assert_eq!(
+            Described(" Ends with \"quotes\"").to_string(),
+            "  \"\"\"\n  Ends with \"quotes\"\n  \"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn comments_are_paragraphs() {
+        let mut location = SourceCodeInfo_Location::new();
+        location.set_leading_detached_comments(::protobuf::RepeatedField::from_vec(vec![
+            " Detached.\n".to_string(),
+        ]));
+        location.set_leading_comments(" Leading,\n on two lines.\n".to_string());
+        location.set_trailing_comments(" Trailing.\n".to_string());
+
+        let mut comments = Comments::default();
+        assert_eq!(
+            from_comments(&location, &comments),
+            "Leading,\non two lines.\n\nTrailing."
+        );
+        comments.detached = true;
+        comments.trailing = false;
+        assert_eq!(
+            from_comments(&location, &comments),
+            "Detached.\n\nLeading,\non two lines."
+        );
+    }
+}
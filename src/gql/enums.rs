@@ -1,8 +1,64 @@
-use protobuf::descriptor::{EnumDescriptorProto, SourceCodeInfo};
+use config::{Config, EnumPrefix, Unspecified};
+use error::ErrorKind;
+use gql::{deprecated_directive, write_description};
+use heck::ShoutySnakeCase;
+use options::GraphqlOptions;
+use protobuf::descriptor::{EnumDescriptorProto, EnumValueDescriptorProto};
+use protobuf::Message;
+use source_info::{Path, SourceInfo};
+
+/// The zero value of an enum, when it is named `UNSPECIFIED` or `<PREFIX>_UNSPECIFIED` as the
+/// style guide has it, to be left out of the schema with `unspecified=null`.
+pub fn unspecified_value(src: &EnumDescriptorProto) -> Option<&EnumValueDescriptorProto> {
+    src.get_value().iter().find(|value| {
+        let name = value.get_name();
+        value.get_number() == 0
+            && (name == "UNSPECIFIED"
+                || name == format!("{}UNSPECIFIED", value_prefix(src.get_name())))
+    })
+}
+
+/// The prefix the style guide gives the values of an enum: `FatType` → `FAT_TYPE_`.
+fn value_prefix(enum_name: &str) -> String {
+    format!("{}_", enum_name.to_shouty_snake_case())
+}
+
+/// Removes the prefix of the enum from the value names. Values whose name would not be valid
+/// without it keep it, and so do all of them when two would end up with the same name. Values
+/// renamed with the `graphql` options keep their name.
+fn strip_prefix(enum_name: &str, values: &mut [EnumField]) {
+    let prefix = value_prefix(enum_name);
+    let stripped: Vec<String> = values
+        .iter()
+        .map(|value| match value.name.strip_prefix(prefix.as_str()) {
+            Some(rest)
+                if value.name == value.proto_name
+                    && rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') =>
+            {
+                rest.to_string()
+            }
+            _ => value.name.clone(),
+        })
+        .collect();
+
+    let distinct = stripped
+        .iter()
+        .enumerate()
+        .all(|(idx, name)| !stripped[..idx].contains(name));
+    if distinct {
+        for (value, name) in values.iter_mut().zip(stripped) {
+            value.name = name;
+        }
+    }
+}
 
 pub struct EnumField {
     pub name: String,
+    /// The name of the value in the proto enum, which is how the grpc stubs know it.
+    pub proto_name: String,
     pub description: String,
+    /// Set when the value is deprecated, empty when there is no reason.
+    pub deprecation_reason: Option<String>,
 }
 
 pub struct EnumType {
@@ -14,56 +70,68 @@ pub struct EnumType {
 impl EnumType {
     pub fn from_proto(
         src: &EnumDescriptorProto,
-        source_info: &SourceCodeInfo,
-        root_path: &[i32],
-        name_prefix: Option<&str>,
-    ) -> EnumType {
-        let description: String = source_info
-            .get_location()
-            .iter()
-            .filter(|loc| loc.get_path() == root_path)
-            .map(|loc| loc.get_leading_comments())
-            .collect();
-        EnumType {
-            description,
-            name: format!("{}{}", name_prefix.unwrap_or(""), src.get_name()),
-            values: src.get_value()
-                .iter()
-                .enumerate()
-                .map(|(idx, v)| {
-                    let name = v.get_name().to_string();
-                    let mut full_path = root_path.to_owned();
-                    full_path.push((idx + 1) as i32);
-                    let description = source_info
-                        .get_location()
-                        .iter()
-                        .filter(|loc| loc.get_path().starts_with(full_path.as_slice()))
-                        .map(|loc| {
-                            format!(
-                                "{}{}",
-                                loc.get_leading_comments(),
-                                loc.get_trailing_comments()
-                            )
-                        })
-                        .collect();
-                    EnumField { name, description }
-                })
-                .collect(),
+        name: String,
+        source_info: &SourceInfo,
+        path: &Path,
+        config: &Config,
+    ) -> Result<EnumType, ErrorKind> {
+        let options = GraphqlOptions::parse(src.get_options().get_unknown_fields())?;
+        let description = source_info.comments(path, &config.comments);
+        let unspecified = match config.unspecified {
+            Unspecified::Keep => None,
+            Unspecified::Null => unspecified_value(src),
+        };
+        let mut values = Vec::new();
+        for (idx, v) in src.get_value().iter().enumerate() {
+            let value_options = GraphqlOptions::parse(v.get_options().get_unknown_fields())?;
+            let is_unspecified = unspecified.is_some_and(|zero| zero.get_name() == v.get_name());
+            if value_options.skip || is_unspecified {
+                continue;
+            }
+
+            let description = source_info.comments(&path.value(idx), &config.comments);
+            values.push(EnumField {
+                deprecation_reason: value_options
+                    .deprecation(v.get_options().get_deprecated(), &description),
+                name: value_options
+                    .name
+                    .unwrap_or_else(|| v.get_name().to_string()),
+                proto_name: v.get_name().to_string(),
+                description: value_options.description.unwrap_or(description),
+            });
         }
+
+        if config.enum_prefix == EnumPrefix::Strip {
+            strip_prefix(src.get_name(), &mut values);
+        }
+
+        Ok(EnumType {
+            description: options.description.unwrap_or(description),
+            name,
+            values,
+        })
+    }
+
+    /// Whether some values are renamed, so the resolvers have to map them to the proto ones.
+    pub fn is_renamed(&self) -> bool {
+        self.values
+            .iter()
+            .any(|value| value.name != value.proto_name)
     }
 }
 
 impl ::std::fmt::Display for EnumType {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        for line in self.description.lines() {
-            write!(formatter, "#{}\n", line)?;
-        }
+        write_description(formatter, &self.description, "")?;
         write!(formatter, "enum {} {{\n", self.name)?;
         for v in self.values.iter() {
-            for line in v.description.lines() {
-                write!(formatter, "  #{}\n", line)?;
-            }
-            write!(formatter, "  {}\n", v.name)?;
+            write_description(formatter, &v.description, "  ")?;
+            write!(
+                formatter,
+                "  {}{}\n",
+                v.name,
+                deprecated_directive(v.deprecation_reason.as_deref())
+            )?;
         }
         write!(formatter, "}}")
     }
@@ -81,15 +149,21 @@ mod tests {
             values: vec![
                 EnumField {
                     name: "red".into(),
+                    proto_name: "red".into(),
                     description: "".to_string(),
+                    deprecation_reason: None,
                 },
                 EnumField {
                     name: "green".into(),
+                    proto_name: "green".into(),
                     description: "".to_string(),
+                    deprecation_reason: None,
                 },
                 EnumField {
                     name: "blue".into(),
+                    proto_name: "blue".into(),
                     description: "".to_string(),
+                    deprecation_reason: None,
                 },
             ],
         };
@@ -99,4 +173,71 @@ mod tests {
             "enum Color {\n  red\n  green\n  blue\n}"
         );
     }
+
+    fn values(names: &[&str]) -> Vec<EnumField> {
+        names
+            .iter()
+            .map(|name| EnumField {
+                name: name.to_string(),
+                proto_name: name.to_string(),
+                description: "".to_string(),
+                deprecation_reason: None,
+            })
+            .collect()
+    }
+
+    fn names(values: &[EnumField]) -> Vec<&str> {
+        values.iter().map(|value| value.name.as_str()).collect()
+    }
+
+    #[test]
+    fn prefixes_are_stripped() {
+        let mut fat_type = values(&[
+            "FAT_TYPE_UNSPECIFIED",
+            "FAT_TYPE_GOOD",
+            "FAT_TYPE_2X",
+            "LARD",
+        ]);
+        fat_type[3].name = "FAT_TYPE_LARD".to_string();
+        strip_prefix("FatType", &mut fat_type);
+        assert_eq!(
+            names(&fat_type),
+            vec!["UNSPECIFIED", "GOOD", "FAT_TYPE_2X", "FAT_TYPE_LARD"]
+        );
+
+        let mut duplicates = values(&["SIZE_SMALL", "SMALL"]);
+        strip_prefix("Size", &mut duplicates);
+        assert_eq!(names(&duplicates), vec!["SIZE_SMALL", "SMALL"]);
+    }
+
+    #[test]
+    fn unspecified_values_are_found() {
+        let enum_ = |name: &str, values: &[(&str, i32)]| {
+            let mut enum_ = EnumDescriptorProto::new();
+            enum_.set_name(name.to_string());
+            for &(name, number) in values {
+                let mut value = EnumValueDescriptorProto::new();
+                value.set_name(name.to_string());
+                value.set_number(number);
+                enum_.mut_value().push(value);
+            }
+            enum_
+        };
+
+        let fat_type = enum_(
+            "FatType",
+            &[("FAT_TYPE_UNSPECIFIED", 0), ("FAT_TYPE_GOOD", 1)],
+        );
+        assert_eq!(
+            unspecified_value(&fat_type).map(|value| value.get_name()),
+            Some("FAT_TYPE_UNSPECIFIED")
+        );
+        let size = enum_("Size", &[("UNSPECIFIED", 0)]);
+        assert!(unspecified_value(&size).is_some());
+        let other = enum_(
+            "Size",
+            &[("FAT_TYPE_UNSPECIFIED", 0), ("SIZE_UNSPECIFIED", 1)],
+        );
+        assert!(unspecified_value(&other).is_none());
+    }
 }
@@ -1,123 +1,178 @@
-use heck::*;
+use config::MapStyle;
+use gql::{write_description, MapEntry, WellKnownType};
 use protobuf::descriptor::*;
-use support;
 
 #[derive(Debug, Clone)]
 pub struct Field {
     pub description: Option<String>,
     pub name: String,
+    /// The name of the field in the proto message, which is how the grpc stubs know it.
+    pub proto_name: String,
     pub type_: FieldType,
     pub required: bool,
+    /// Set when the field is deprecated, empty when there is no reason.
+    pub deprecation_reason: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct FieldType {
     pub proto_type: FieldDescriptorProto_Type,
+    /// The proto type name for messages, groups and enums, the GraphQL scalar name for scalar types.
     pub type_name: String,
     pub label: FieldDescriptorProto_Label,
+    /// Set when the field's message type has a dedicated GraphQL representation. For wrappers,
+    /// `proto_type` and `type_name` are the ones of the wrapped value.
+    pub well_known: Option<WellKnownType>,
+    /// Set for map fields, whose `type_name` is the entry message.
+    pub map_entry: Option<Box<MapEntry>>,
+    /// The GraphQL name of the message, group or enum, as given by the type naming, or of the
+    /// type made up by the generator. Unset for scalars and the well-known types.
+    pub gql_name: Option<String>,
+    /// For enums whose zero value is left out of the schema, the proto name of that value, which
+    /// the resolvers turn into null.
+    pub unspecified: Option<String>,
+    /// For enums, the proto name of the value numbered 0, which grpc leaves out of the messages
+    /// it hands out like the other zero values.
+    pub zero_value: Option<String>,
+}
+
+/// The `@deprecated` directive with the given reason, with a leading space, or nothing. An empty
+/// reason leaves the default one of the directive.
+pub fn deprecated_directive(reason: Option<&str>) -> String {
+    match reason {
+        Some("") => " @deprecated".to_string(),
+        Some(reason) => format!(
+            " @deprecated(reason: \"{}\")",
+            reason
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        ),
+        None => String::new(),
+    }
 }
 
 impl FieldType {
-    fn format_required(
-        &self,
-        formatter: &mut ::std::fmt::Formatter,
-    ) -> Result<(), ::std::fmt::Error> {
-        let repeated = self.label == FieldDescriptorProto_Label::LABEL_REPEATED;
+    pub fn is_scalar(&self) -> bool {
+        self.well_known.is_some()
+            || self.is_json_map()
+            || !matches!(
+                self.proto_type,
+                FieldDescriptorProto_Type::TYPE_MESSAGE
+                    | FieldDescriptorProto_Type::TYPE_GROUP
+                    | FieldDescriptorProto_Type::TYPE_ENUM
+            )
+    }
+
+    /// Whether the field holds a message, including the well-known types.
+    pub fn is_message(&self) -> bool {
+        self.well_known.is_some()
+            || matches!(
+                self.proto_type,
+                FieldDescriptorProto_Type::TYPE_MESSAGE | FieldDescriptorProto_Type::TYPE_GROUP
+            )
+    }
+
+    /// Whether the field is a map rendered as a `JSON` object.
+    pub fn is_json_map(&self) -> bool {
+        match self.map_entry {
+            Some(ref map_entry) => map_entry.style == MapStyle::Json,
+            None => false,
+        }
+    }
+
+    pub fn is_repeated(&self) -> bool {
+        self.label == FieldDescriptorProto_Label::LABEL_REPEATED
+    }
+
+    /// Whether the field is rendered as an object type, with a separate input type.
+    pub fn is_object(&self) -> bool {
+        !self.is_scalar() && self.proto_type != FieldDescriptorProto_Type::TYPE_ENUM
+    }
 
-        if repeated {
-            write!(formatter, "[")?;
+    /// The named GraphQL type of the field, without list wrapper and `Input` suffix.
+    pub fn named_type(&self) -> String {
+        if let Some(gql_type) = self.well_known.and_then(|well_known| well_known.gql_type()) {
+            return gql_type.to_string();
+        }
+
+        if self.is_json_map() {
+            return "JSON".to_string();
         }
 
         match self.proto_type {
-            FieldDescriptorProto_Type::TYPE_BOOL => write!(formatter, "{}", "Boolean"),
-            FieldDescriptorProto_Type::TYPE_STRING => write!(formatter, "{}", "String"),
-            FieldDescriptorProto_Type::TYPE_INT32
-            | FieldDescriptorProto_Type::TYPE_INT64
-            | FieldDescriptorProto_Type::TYPE_UINT32
-            | FieldDescriptorProto_Type::TYPE_UINT64 => write!(formatter, "Int"),
-            FieldDescriptorProto_Type::TYPE_FLOAT | FieldDescriptorProto_Type::TYPE_DOUBLE => {
-                write!(formatter, "Float")
+            FieldDescriptorProto_Type::TYPE_MESSAGE
+            | FieldDescriptorProto_Type::TYPE_GROUP
+            | FieldDescriptorProto_Type::TYPE_ENUM => self
+                .gql_name
+                .clone()
+                .expect("messages, groups and enums have a GraphQL name"),
+            _ => self.type_name.clone(),
+        }
+    }
+
+    /// The message or enum it references, for the types that are rendered as such.
+    pub fn referenced_type(&self) -> Option<&str> {
+        if let Some(ref map_entry) = self.map_entry {
+            return map_entry.value.referenced_type();
+        }
+
+        match self.proto_type {
+            FieldDescriptorProto_Type::TYPE_MESSAGE
+            | FieldDescriptorProto_Type::TYPE_GROUP
+            | FieldDescriptorProto_Type::TYPE_ENUM
+                if self.well_known.is_none() =>
+            {
+                Some(&self.type_name)
             }
-            FieldDescriptorProto_Type::TYPE_MESSAGE => write!(
-                formatter,
-                "{}",
-                support::strip_leading_dots(&self.type_name)
-                    .replace(".", "_")
-                    .to_camel_case()
-            ),
-            FieldDescriptorProto_Type::TYPE_ENUM => write!(
-                formatter,
-                "{}",
-                &self.type_name.replace(".", "_").to_camel_case()
-            ),
-            t => unimplemented!("Unhandled type {:?}", t),
-        }?;
+            _ => None,
+        }
+    }
 
-        if repeated {
-            write!(formatter, "]")?;
+    /// The GraphQL type of the field, without the non-null marker. Message types are
+    /// suffixed with `Input` when `input` is true.
+    pub fn gql_type(&self, input: bool) -> String {
+        let named = match self.well_known {
+            Some(WellKnownType::FieldMask) => format!("[{}!]", self.named_type()),
+            _ if input && self.is_object() => format!("{}Input", self.named_type()),
+            _ => self.named_type(),
+        };
+
+        if self.is_json_map() {
+            named
+        } else if self.is_repeated() {
+            format!("[{}!]", named)
+        } else {
+            named
         }
-        Ok(())
     }
+}
 
-    fn format_optional(
+impl Field {
+    /// Writes the field as declared in an object type, or in an input type when `input` is true.
+    pub fn write(
         &self,
         formatter: &mut ::std::fmt::Formatter,
+        input: bool,
     ) -> Result<(), ::std::fmt::Error> {
-        let repeated = self.label == FieldDescriptorProto_Label::LABEL_REPEATED;
-
-        if repeated {
-            write!(formatter, "[")?;
-        }
-
-        match self.proto_type {
-            FieldDescriptorProto_Type::TYPE_BOOL => write!(formatter, "{}", "Boolean"),
-            FieldDescriptorProto_Type::TYPE_STRING => write!(formatter, "{}", "String"),
-            FieldDescriptorProto_Type::TYPE_INT32
-            | FieldDescriptorProto_Type::TYPE_INT64
-            | FieldDescriptorProto_Type::TYPE_UINT32
-            | FieldDescriptorProto_Type::TYPE_UINT64 => write!(formatter, "Int"),
-            FieldDescriptorProto_Type::TYPE_FLOAT | FieldDescriptorProto_Type::TYPE_DOUBLE => {
-                write!(formatter, "Float")
-            }
-            FieldDescriptorProto_Type::TYPE_MESSAGE => write!(
-                formatter,
-                "{}Input",
-                support::strip_leading_dots(&self.type_name)
-                    .replace(".", "_")
-                    .to_camel_case()
-            ),
-            FieldDescriptorProto_Type::TYPE_ENUM => write!(
+        write_description(formatter, self.description.as_deref().unwrap_or(""), "  ")?;
+        write!(formatter, "  {}: {}", self.name, self.type_.gql_type(input))?;
+        write!(formatter, "{}", if self.required { "!" } else { "" })?;
+        // Required input fields cannot be deprecated, since clients have to set them.
+        if !input || !self.required {
+            write!(
                 formatter,
                 "{}",
-                &self.type_name.replace(".", "_").to_camel_case()
-            ),
-            t => unimplemented!("Unhandled type {:?}", t),
-        }?;
-
-        if repeated {
-            write!(formatter, "]")?;
+                deprecated_directive(self.deprecation_reason.as_deref())
+            )?;
         }
-
-        Ok(())
+        writeln!(formatter)
     }
 }
 
 impl ::std::fmt::Display for Field {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        let comment = self.description.clone().unwrap_or("".to_string());
-        for line in comment.lines() {
-            write!(formatter, "  #{}\n", line)?;
-        }
-
-        write!(formatter, "  {}: ", self.name,)?;
-
-        if self.required {
-            self.type_.format_required(formatter)?
-        } else {
-            self.type_.format_optional(formatter)?
-        }
-
-        write!(formatter, "{}\n", if self.required { "!" } else { "" })
+        self.write(formatter, false)
     }
 }
 
@@ -129,17 +184,33 @@ mod tests {
     fn field_display() {
         let ty = FieldType {
             proto_type: FieldDescriptorProto_Type::TYPE_MESSAGE,
-            type_name: "Cat".to_string(),
+            type_name: ".Cat".to_string(),
             label: FieldDescriptorProto_Label::LABEL_OPTIONAL,
+            well_known: None,
+            map_entry: None,
+            gql_name: Some("Cat".to_string()),
+            unspecified: None,
+            zero_value: None,
         };
 
         let field = Field {
             description: None,
             name: "feline".to_string(),
+            proto_name: "feline".to_string(),
             type_: ty,
             required: true,
+            deprecation_reason: None,
         };
 
         assert_eq!(field.to_string(), "  feline: Cat!\n");
+
+        let field = Field {
+            deprecation_reason: Some("use \"cat\"".to_string()),
+            ..field
+        };
+        assert_eq!(
+            field.to_string(),
+            "  feline: Cat! @deprecated(reason: \"use \\\"cat\\\"\")\n"
+        );
     }
 }
@@ -1,19 +1,25 @@
-use gql::{Field, ObjectType};
+use gql::{write_description, Field, ObjectType};
 
 pub struct InputType {
     pub name: String,
     pub fields: Vec<Field>,
     pub description: Option<String>,
+    /// Whether exactly one of its fields must be set, per the `@oneOf` input object convention.
+    pub one_of: bool,
 }
 
 impl ::std::fmt::Display for InputType {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        let fields: String = self.fields.iter().map(|f| format!("{}", f)).collect();
-        let comment = self.description.clone().unwrap_or("".to_string());
-        for line in comment.lines() {
-            write!(formatter, "#{}\n", line)?;
+        write_description(formatter, self.description.as_deref().unwrap_or(""), "")?;
+        write!(formatter, "input {}Input", self.name)?;
+        if self.one_of {
+            write!(formatter, " @oneOf")?;
         }
-        write!(formatter, "input {}Input {{\n{}}}", self.name, fields)
+        writeln!(formatter, " {{")?;
+        for field in self.fields.iter() {
+            field.write(formatter, true)?;
+        }
+        write!(formatter, "}}")
     }
 }
 
@@ -28,8 +34,10 @@ impl ::std::convert::From<ObjectType> for InputType {
                     required: false,
                     ..f.clone()
                 })
+                .chain(input.oneofs.iter().map(|oneof| oneof.input_field()))
                 .collect(),
             description: input.description.clone(),
+            one_of: false,
         }
     }
 }
@@ -0,0 +1,48 @@
+use config::MapStyle;
+use gql::{Field, FieldType, InputType, ObjectType};
+
+/// The key and value types of a map field, whose proto type is a synthesized `*Entry` message.
+#[derive(Debug, Clone)]
+pub struct MapEntry {
+    pub key: FieldType,
+    pub value: FieldType,
+    pub style: MapStyle,
+}
+
+impl MapEntry {
+    /// The key and the value, with the value nullable in output types when it is an enum whose
+    /// zero value reads as null.
+    fn fields(&self, output: bool) -> Vec<Field> {
+        [("key", &self.key), ("value", &self.value)]
+            .iter()
+            .map(|&(name, type_)| Field {
+                description: None,
+                name: name.to_string(),
+                proto_name: name.to_string(),
+                type_: type_.clone(),
+                required: !output || type_.unspecified.is_none(),
+                deprecation_reason: None,
+            })
+            .collect()
+    }
+
+    /// The entry type, named after the entry message, for the `entries` style.
+    pub fn object_type(&self, name: &str) -> ObjectType {
+        ObjectType {
+            name: name.to_string(),
+            fields: self.fields(true),
+            oneofs: Vec::new(),
+            description: None,
+        }
+    }
+
+    /// Unlike the input types of messages, both the key and the value are required.
+    pub fn input_type(&self, name: &str) -> InputType {
+        InputType {
+            name: name.to_string(),
+            fields: self.fields(false),
+            description: None,
+            one_of: false,
+        }
+    }
+}
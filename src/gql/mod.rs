@@ -1,13 +1,23 @@
+mod description;
 mod enums;
 mod field;
 mod input;
+mod map;
 mod object;
+mod oneof;
+mod scalar;
 mod service;
 mod type_defs;
+mod well_known;
 
+pub use self::description::*;
 pub use self::enums::*;
 pub use self::field::*;
 pub use self::input::*;
+pub use self::map::*;
 pub use self::object::*;
+pub use self::oneof::*;
+pub use self::scalar::*;
 pub use self::service::*;
 pub use self::type_defs::*;
+pub use self::well_known::*;
@@ -1,20 +1,70 @@
-use gql::Field;
+use gql::{write_description, Field, FieldType, MapEntry, Oneof};
 
 #[derive(Debug, Clone)]
 pub struct ObjectType {
     pub name: String,
+    /// The fields that are not part of a oneof
     pub fields: Vec<Field>,
+    pub oneofs: Vec<Oneof>,
     pub description: Option<String>,
 }
 
+impl ObjectType {
+    /// Its fields, including the members of its oneofs.
+    pub fn all_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields
+            .iter()
+            .chain(self.oneofs.iter().flat_map(|oneof| oneof.fields.iter()))
+    }
+
+    /// The types of all its fields, including the key and value types of the maps rendered as
+    /// entry lists.
+    pub fn field_types(&self) -> Vec<&FieldType> {
+        let mut types = Vec::new();
+        for field in self.all_fields() {
+            types.push(&field.type_);
+        }
+        for (_, map_entry) in self.map_entries() {
+            types.push(&map_entry.key);
+            types.push(&map_entry.value);
+        }
+        types
+    }
+
+    /// The messages and enums its fields reference.
+    pub fn referenced_types(&self) -> Vec<&str> {
+        self.all_fields()
+            .filter_map(|field| field.type_.referenced_type())
+            .collect()
+    }
+
+    /// The entries of the maps rendered as lists of entry types, with the entry type names.
+    pub fn map_entries(&self) -> impl Iterator<Item = (String, &MapEntry)> {
+        self.all_fields()
+            .filter(|field| !field.type_.is_json_map())
+            .filter_map(|field| {
+                field
+                    .type_
+                    .map_entry
+                    .as_ref()
+                    .map(|entry| (field.type_.named_type(), &**entry))
+            })
+    }
+}
+
 impl ::std::fmt::Display for ObjectType {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        let fields: String = self.fields.iter().map(|f| format!("{}", f)).collect();
-        let comment = self.description.clone().unwrap_or("".to_string());
-        for line in comment.lines() {
-            write!(formatter, "#{}\n", line)?;
+        write_description(formatter, self.description.as_deref().unwrap_or(""), "")?;
+        write!(formatter, "type {} {{\n", self.name)?;
+        for field in self.fields.iter() {
+            write!(formatter, "{}", field)?;
+        }
+        for oneof in self.oneofs.iter() {
+            for field in oneof.object_fields() {
+                write!(formatter, "{}", field)?;
+            }
         }
-        write!(formatter, "type {} {{\n{}}}", self.name, fields)
+        write!(formatter, "}}")
     }
 }
 
@@ -28,20 +78,28 @@ mod tests {
     fn object_type_display() {
         let ty = FieldType {
             proto_type: FieldDescriptorProto_Type::TYPE_MESSAGE,
-            type_name: "Cat".to_string(),
+            type_name: ".Cat".to_string(),
             label: FieldDescriptorProto_Label::LABEL_OPTIONAL,
+            well_known: None,
+            map_entry: None,
+            gql_name: Some("Cat".to_string()),
+            unspecified: None,
+            zero_value: None,
         };
 
         let field = Field {
             description: None,
             name: "feline".to_string(),
+            proto_name: "feline".to_string(),
             type_: ty,
             required: true,
+            deprecation_reason: None,
         };
 
         let object = ObjectType {
             name: "Pet".to_string(),
             fields: vec![field],
+            oneofs: Vec::new(),
             description: None,
         };
 
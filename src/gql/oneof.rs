@@ -0,0 +1,248 @@
+use config::OneofStyle;
+use gql::{write_description, EnumField, EnumType, Field, FieldType, InputType, ObjectType};
+use heck::*;
+use protobuf::descriptor::*;
+
+/// A `oneof` group of a message, whose members are kept out of the object's regular fields.
+#[derive(Debug, Clone)]
+pub struct Oneof {
+    pub description: Option<String>,
+    /// The name of the field holding the oneof in the object type
+    pub name: String,
+    pub proto_name: String,
+    /// The name of the union or the prefix of the case enum, and of the `@oneOf` input type
+    pub type_name: String,
+    pub style: OneofStyle,
+    pub fields: Vec<Field>,
+}
+
+impl Oneof {
+    pub fn case_enum_name(&self) -> String {
+        format!("{}Case", self.type_name)
+    }
+
+    /// The value of the case enum for a member.
+    pub fn case_name(member: &Field) -> String {
+        member.proto_name.to_shouty_snake_case()
+    }
+
+    /// The name of the union member type wrapping a member.
+    pub fn member_type_name(&self, member: &Field) -> String {
+        format!("{}{}", self.type_name, member.proto_name.to_camel_case())
+    }
+
+    /// The names of the output types it needs besides its object type.
+    pub fn output_type_names(&self) -> Vec<String> {
+        match self.style {
+            OneofStyle::Fields => vec![self.case_enum_name()],
+            OneofStyle::Union => ::std::iter::once(self.type_name.clone())
+                .chain(
+                    self.fields
+                        .iter()
+                        .map(|member| self.member_type_name(member)),
+                )
+                .collect(),
+        }
+    }
+
+    /// The enum telling which member is set, for the `fields` style.
+    pub fn case_enum(&self) -> EnumType {
+        EnumType {
+            description: String::new(),
+            name: self.case_enum_name(),
+            values: self
+                .fields
+                .iter()
+                .map(|member| EnumField {
+                    name: Oneof::case_name(member),
+                    proto_name: Oneof::case_name(member),
+                    description: String::new(),
+                    deprecation_reason: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The object types of the union, for the `union` style.
+    pub fn member_types(&self) -> Vec<ObjectType> {
+        self.fields
+            .iter()
+            .map(|member| ObjectType {
+                name: self.member_type_name(member),
+                // An unspecified enum value is converted to null, so it can't be required.
+                fields: vec![Field {
+                    required: member.type_.unspecified.is_none(),
+                    ..member.clone()
+                }],
+                oneofs: Vec::new(),
+                description: None,
+            })
+            .collect()
+    }
+
+    pub fn input_type(&self) -> InputType {
+        InputType {
+            name: self.type_name.clone(),
+            fields: self
+                .fields
+                .iter()
+                .map(|member| Field {
+                    required: false,
+                    ..member.clone()
+                })
+                .collect(),
+            description: self.description.clone(),
+            one_of: true,
+        }
+    }
+
+    /// The field referencing the union or the input type.
+    pub fn field(&self, proto_type: FieldDescriptorProto_Type, type_name: String) -> Field {
+        Field {
+            description: self.description.clone(),
+            name: self.name.clone(),
+            proto_name: self.proto_name.clone(),
+            type_: FieldType {
+                proto_type,
+                type_name: type_name.clone(),
+                label: FieldDescriptorProto_Label::LABEL_OPTIONAL,
+                well_known: None,
+                map_entry: None,
+                gql_name: Some(type_name),
+                unspecified: None,
+                zero_value: None,
+            },
+            required: false,
+            deprecation_reason: None,
+        }
+    }
+
+    /// The fields it contributes to its object type.
+    pub fn object_fields(&self) -> Vec<Field> {
+        match self.style {
+            OneofStyle::Fields => {
+                let mut fields: Vec<Field> = self
+                    .fields
+                    .iter()
+                    .map(|member| Field {
+                        required: false,
+                        ..member.clone()
+                    })
+                    .collect();
+                fields
+                    .push(self.field(FieldDescriptorProto_Type::TYPE_ENUM, self.case_enum_name()));
+                fields
+            }
+            OneofStyle::Union => vec![self.field(
+                FieldDescriptorProto_Type::TYPE_MESSAGE,
+                self.type_name.clone(),
+            )],
+        }
+    }
+
+    /// The field it contributes to the input type of its object.
+    pub fn input_field(&self) -> Field {
+        self.field(
+            FieldDescriptorProto_Type::TYPE_MESSAGE,
+            self.type_name.clone(),
+        )
+    }
+}
+
+/// Renders the output types it needs besides its object type: the case enum or the union and its
+/// members.
+impl ::std::fmt::Display for Oneof {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        match self.style {
+            OneofStyle::Fields => write!(formatter, "{}", self.case_enum()),
+            OneofStyle::Union => {
+                let members = self.member_types();
+                write_description(formatter, self.description.as_deref().unwrap_or(""), "")?;
+                write!(
+                    formatter,
+                    "union {} = {}",
+                    self.type_name,
+                    members
+                        .iter()
+                        .map(|member| member.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                )?;
+                for member in members.iter() {
+                    write!(formatter, "\n\n{}", member)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(name: &str, type_name: &str) -> Field {
+        Field {
+            description: None,
+            name: name.to_string(),
+            proto_name: name.to_string(),
+            type_: FieldType {
+                proto_type: FieldDescriptorProto_Type::TYPE_STRING,
+                type_name: type_name.to_string(),
+                label: FieldDescriptorProto_Label::LABEL_OPTIONAL,
+                well_known: None,
+                map_entry: None,
+                gql_name: None,
+                unspecified: None,
+                zero_value: None,
+            },
+            required: true,
+            deprecation_reason: None,
+        }
+    }
+
+    #[test]
+    fn oneof_display() {
+        let mut oneof = Oneof {
+            description: None,
+            name: "base".to_string(),
+            proto_name: "base".to_string(),
+            type_name: "PizzaBase".to_string(),
+            style: OneofStyle::Fields,
+            fields: vec![member("tomato_sauce", "String"), member("cream", "Boolean")],
+        };
+
+        assert_eq!(
+            oneof.to_string(),
+            "enum PizzaBaseCase {\n  TOMATO_SAUCE\n  CREAM\n}"
+        );
+        assert_eq!(
+            oneof.input_type().to_string(),
+            "input PizzaBaseInput @oneOf {\n  tomato_sauce: String\n  cream: Boolean\n}"
+        );
+
+        oneof.style = OneofStyle::Union;
+        assert_eq!(
+            oneof.to_string(),
+            "union PizzaBase = PizzaBaseTomatoSauce | PizzaBaseCream
+
+type PizzaBaseTomatoSauce {
+  tomato_sauce: String!
+}
+
+type PizzaBaseCream {
+  cream: Boolean!
+}"
+        );
+
+        let mut size = member("size", "Size");
+        size.type_.proto_type = FieldDescriptorProto_Type::TYPE_ENUM;
+        size.type_.gql_name = Some("Size".to_string());
+        size.type_.unspecified = Some("SIZE_UNSPECIFIED".to_string());
+        oneof.fields = vec![size];
+        assert_eq!(
+            oneof.member_types()[0].to_string(),
+            "type PizzaBaseSize {\n  size: Size\n}"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use gql::{write_description, WellKnownType};
+use protobuf::descriptor::FieldDescriptorProto_Type;
+
+/// A scalar that is not built into GraphQL, and has to be declared in the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomScalar {
+    pub name: String,
+    /// The proto type the scalar stands for, which determines its JS implementation.
+    pub proto_type: FieldDescriptorProto_Type,
+    pub well_known: Option<WellKnownType>,
+}
+
+/// The scalars every GraphQL implementation provides.
+pub const BUILTIN_SCALARS: &[&str] = &["Int", "Float", "String", "Boolean", "ID"];
+
+/// Validates an integer received as a string or a number against a range, and returns it as a
+/// string so 64 bit values make it to the grpc stub without losing precision.
+pub const JS_PARSE_INTEGER: &str = r#"const parseInteger = (scalar, min, max) => value => {
+  const valid = (typeof value === 'string' || Number.isInteger(value)) && /^-?\d+$/.test(String(value))
+  if (!valid || BigInt(value) < BigInt(min) || BigInt(value) > BigInt(max)) {
+    throw new TypeError(`${scalar} cannot represent ${JSON.stringify(value)}`)
+  }
+  return String(value)
+}
+"#;
+
+/// Parses JSON written inline in a query.
+const JS_PARSE_JSON_LITERAL: &str = r#"function parseLiteral(ast) {
+  switch (ast.kind) {
+    case Kind.STRING:
+    case Kind.BOOLEAN: return ast.value
+    case Kind.INT:
+    case Kind.FLOAT: return Number(ast.value)
+    case Kind.LIST: return ast.values.map(parseLiteral)
+    case Kind.OBJECT: {
+      const object = {}
+      ast.fields.forEach(field => { object[field.name.value] = parseLiteral(field.value) })
+      return object
+    }
+    default: return null
+  }
+}"#;
+
+/// Validates a string against a regex, for the scalars represented as formatted strings.
+fn js_parse_string(scalar: &str, regex: &str) -> String {
+    format!(
+        "value => {{
+  if (typeof value !== 'string' || !{regex}.test(value)) {{
+    throw new TypeError(`{scalar} cannot represent ${{JSON.stringify(value)}}`)
+  }}
+  return value
+}}",
+        scalar = scalar,
+        regex = regex
+    )
+}
+
+/// The range of an integer proto type, and whether its values are serialized as strings because
+/// they do not fit in a JS number.
+fn integer_range(
+    proto_type: FieldDescriptorProto_Type,
+) -> Option<(&'static str, &'static str, bool)> {
+    use self::FieldDescriptorProto_Type::*;
+
+    match proto_type {
+        TYPE_INT32 | TYPE_SINT32 | TYPE_SFIXED32 => Some(("-2147483648", "2147483647", false)),
+        TYPE_UINT32 | TYPE_FIXED32 => Some(("0", "4294967295", false)),
+        TYPE_INT64 | TYPE_SINT64 | TYPE_SFIXED64 => {
+            Some(("-9223372036854775808", "9223372036854775807", true))
+        }
+        TYPE_UINT64 | TYPE_FIXED64 => Some(("0", "18446744073709551615", true)),
+        _ => None,
+    }
+}
+
+/// The functions of a `GraphQLScalarType`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsScalarImplementation {
+    pub serialize: String,
+    pub parse_value: String,
+    /// The kinds of literals (`INT`, `STRING`...) whose value is passed to `parse_value`, when
+    /// there is no custom `parse_literal`.
+    pub literal_kinds: &'static [&'static str],
+    pub parse_literal: Option<&'static str>,
+}
+
+impl CustomScalar {
+    /// Whether the JS implementation relies on [`JS_PARSE_INTEGER`].
+    pub fn is_integer(&self) -> bool {
+        integer_range(self.proto_type).is_some()
+    }
+
+    /// The implementation of the `GraphQLScalarType` for this scalar, if it needs anything else
+    /// than passing values through.
+    pub fn js_implementation(&self) -> Option<JsScalarImplementation> {
+        if let Some((min, max, as_string)) = integer_range(self.proto_type) {
+            let parse = format!("parseInteger('{}', '{}', '{}')", self.name, min, max);
+            // 64 bit integers arrive from grpc as strings or Long objects, both of which have a
+            // lossless toString.
+            let (serialize, parse_value) = if as_string {
+                ("value => value.toString()".to_string(), parse)
+            } else {
+                (
+                    "value => Number(value)".to_string(),
+                    format!("value => Number({}(value))", parse),
+                )
+            };
+            return Some(JsScalarImplementation {
+                serialize,
+                parse_value,
+                literal_kinds: &["INT", "STRING"],
+                parse_literal: None,
+            });
+        }
+
+        // The well-known types are converted from and to their messages by the codecs in the
+        // resolvers, so their scalars only validate the values. Wrappers share the scalars of
+        // the types they wrap.
+        match self.well_known {
+            Some(WellKnownType::Timestamp) => {
+                return Some(JsScalarImplementation {
+                    serialize: "value => value".to_string(),
+                    parse_value: js_parse_string(
+                        &self.name,
+                        r"/^\d{4}-\d\d-\d\dT\d\d:\d\d:\d\d(\.\d{1,9})?(Z|[+-]\d\d:\d\d)$/i",
+                    ),
+                    literal_kinds: &["STRING"],
+                    parse_literal: None,
+                })
+            }
+            Some(WellKnownType::Duration) => {
+                return Some(JsScalarImplementation {
+                    serialize: "value => value".to_string(),
+                    parse_value: js_parse_string(&self.name, r"/^-?\d+(\.\d{1,9})?s$/"),
+                    literal_kinds: &["STRING"],
+                    parse_literal: None,
+                })
+            }
+            Some(WellKnownType::Wrapper(_)) | None => (),
+            Some(_) => {
+                return Some(JsScalarImplementation {
+                    serialize: "value => value".to_string(),
+                    parse_value: "value => value".to_string(),
+                    literal_kinds: &[],
+                    parse_literal: Some(JS_PARSE_JSON_LITERAL),
+                })
+            }
+        }
+
+        match self.proto_type {
+            // grpc hands out bytes fields as Buffers
+            FieldDescriptorProto_Type::TYPE_BYTES => Some(JsScalarImplementation {
+                serialize: "value => Buffer.from(value).toString('base64')".to_string(),
+                parse_value: "value => Buffer.from(value, 'base64')".to_string(),
+                literal_kinds: &["STRING"],
+                parse_literal: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl ::std::fmt::Display for CustomScalar {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        if self.well_known == Some(WellKnownType::Any) {
+            write_description(
+                formatter,
+                "Any JSON value. A `google.protobuf.Any` is an object with the URL of its type under \
+                 \"@type\" and its base64-encoded message under \"value\".",
+                "",
+            )?;
+        }
+        write!(formatter, "scalar {}", self.name)
+    }
+}
@@ -1,29 +1,161 @@
-use heck::*;
+use gql::{deprecated_directive, write_description, FieldType};
+use heck::{CamelCase, MixedCase};
 use protobuf::descriptor::*;
 
 #[derive(Debug, Clone)]
 pub struct Service {
     pub name: String,
-    pub methods: Vec<MethodDescriptorProto>,
+    /// The name of the service in the proto file, which is how grpc knows it.
+    pub proto_name: String,
+    pub description: Option<String>,
+    pub methods: Vec<Method>,
     /// Which file it comes from
     pub origin_file_name: String,
+    /// The package of that file, which `@grpc/grpc-js` loads the service under.
+    pub package: String,
 }
 
+/// The root type a method is exposed under.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Query,
+    Mutation,
+    /// Server streaming methods, which stream their responses
+    Subscription,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub descriptor: MethodDescriptorProto,
+    /// The name of its field in the service type.
+    pub name: String,
+    /// Whether `name` is given by the `graphql` options, in which case it also names the root
+    /// field of the method.
+    pub renamed: bool,
+    pub description: Option<String>,
+    /// Set when the method is deprecated, empty when there is no reason.
+    pub deprecation_reason: Option<String>,
+    /// The name of the argument holding the request, `None` for methods taking
+    /// `google.protobuf.Empty`.
+    pub argument_name: Option<String>,
+    /// The request type, as if it were a message field.
+    pub input_type: FieldType,
+    /// The response type, as if it were a message field.
+    pub output_type: FieldType,
+    pub operation: Operation,
+}
+
+/// Renders the field of a method, named `name`.
+fn write_method(
+    formatter: &mut ::std::fmt::Formatter,
+    method: &Method,
+    name: &str,
+) -> Result<(), ::std::fmt::Error> {
+    writeln!(formatter)?;
+    write_description(formatter, method.description.as_deref().unwrap_or(""), "  ")?;
+    write!(formatter, "  {}", name)?;
+    if let Some(argument_name) = method.argument_name.as_ref() {
+        write!(
+            formatter,
+            "({}: {}!)",
+            argument_name,
+            method.input_type.gql_type(true),
+        )?;
+    }
+    write!(
+        formatter,
+        ": {}!{}",
+        method.output_type.gql_type(false),
+        deprecated_directive(method.deprecation_reason.as_deref())
+    )
+}
+
+impl Service {
+    /// The name of the type holding its queries or its mutations. Subscriptions have none, they
+    /// are fields of the `Subscription` root type.
+    pub fn type_name(&self, operation: Operation) -> String {
+        match operation {
+            Operation::Query => format!("{}Service", self.name),
+            Operation::Mutation => format!("{}ServiceMutations", self.name),
+            Operation::Subscription => unreachable!("subscriptions are root fields"),
+        }
+    }
+
+    /// The name of the field of a method in a root type: `pizzeriaObservePizzas`, or the name
+    /// given by the `graphql` options.
+    pub fn root_field_name(&self, method: &Method) -> String {
+        if method.renamed {
+            method.name.clone()
+        } else {
+            format!(
+                "{}{}",
+                self.name.to_mixed_case(),
+                method.name.to_camel_case()
+            )
+        }
+    }
+
+    /// Whether it has queries or mutations, grouped in types of its own unless flattened.
+    pub fn has_types(&self) -> bool {
+        self.methods
+            .iter()
+            .any(|method| method.operation != Operation::Subscription)
+    }
+
+    pub fn methods_of(&self, operation: Operation) -> Vec<&Method> {
+        self.methods
+            .iter()
+            .filter(|method| method.operation == operation)
+            .collect()
+    }
+
+    fn write_type(
+        &self,
+        formatter: &mut ::std::fmt::Formatter,
+        operation: Operation,
+    ) -> Result<(), ::std::fmt::Error> {
+        write_description(formatter, self.description.as_deref().unwrap_or(""), "")?;
+        write!(formatter, "type {} {{", self.type_name(operation))?;
+
+        for method in self.methods_of(operation) {
+            write_method(formatter, method, &method.name)?;
+        }
+
+        write!(formatter, "\n}}")
+    }
+}
+
+/// Renders the types holding its queries and its mutations, leaving out the empty ones.
 impl ::std::fmt::Display for Service {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        write!(formatter, "type {}Service {{", self.name,)?;
-
-        for method in self.methods.iter() {
-            write!(
-                formatter,
-                "\n  {}({}: {}Input!): {}!",
-                method.get_name().to_mixed_case(),
-                method.get_input_type().to_snake_case(),
-                method.get_input_type().to_camel_case(),
-                method.get_output_type().to_camel_case(),
-            )?;
+        let mut separator = "";
+        for &operation in [Operation::Query, Operation::Mutation].iter() {
+            if !self.methods_of(operation).is_empty() {
+                write!(formatter, "{}", separator)?;
+                self.write_type(formatter, operation)?;
+                separator = "\n\n";
+            }
         }
 
+        Ok(())
+    }
+}
+
+/// A root type with a field per method of the operation, rather than per service. Subscriptions
+/// are always rendered this way, since GraphQL only subscribes to root fields.
+pub struct RootType<'a> {
+    pub operation: Operation,
+    pub services: &'a [Service],
+}
+
+impl<'a> ::std::fmt::Display for RootType<'a> {
+    fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(formatter, "type {:?} {{", self.operation)?;
+        for service in self.services.iter() {
+            for method in service.methods_of(self.operation) {
+                write_method(formatter, method, &service.root_field_name(method))?;
+            }
+        }
         write!(formatter, "\n}}")
     }
 }
@@ -1,68 +1,403 @@
-use gql::{EnumType, Field, FieldType, InputType, ObjectType, Service};
+use config::{Config, Grpc, OneofStyle, RootFields};
+use error::ErrorKind;
+use gql::{
+    CustomScalar, EnumType, Field, FieldType, InputType, JsScalarImplementation, Method,
+    ObjectType, Oneof, Operation, RootType, Service, WellKnownType, BUILTIN_SCALARS,
+    JS_PARSE_INTEGER,
+};
 use heck::*;
+use js::codecs::{self, Direction};
+use js::streams;
 use protobuf::descriptor::*;
+use std::collections::HashMap;
+
+/// The destructured resolver arguments of a method, and the JS expression of the grpc request
+/// built from them.
+fn request_arguments(method: &Method) -> (String, String) {
+    match method.argument_name.as_ref() {
+        Some(argument_name) => (
+            format!("{{ {}: req }}", argument_name),
+            codecs::convert(&method.input_type, Direction::ToGrpc, "req"),
+        ),
+        None => ("_".to_string(), "{}".to_string()),
+    }
+}
+
+/// The statements of a resolver calling a query or a mutation with `request`, which return a
+/// promise of the converted response, separated by a line break and `indent`. The requests of
+/// client streaming methods are written to the call one after the other.
+fn method_call(service: &Service, method: &Method, request: &str, indent: &str) -> String {
+    let callback = format!(
+        "(err, res) => err ? reject(err) : resolve({})",
+        codecs::convert(&method.output_type, Direction::FromGrpc, "res")
+    );
+    if !method.descriptor.get_client_streaming() {
+        return format!(
+            "return new Promise((resolve, reject) => {}Stub.{}({}, {}))",
+            service.name,
+            method.descriptor.get_name(),
+            request,
+            callback
+        );
+    }
+
+    let mut statements = vec![
+        "return new Promise((resolve, reject) => {".to_string(),
+        format!(
+            "  const call = {}Stub.{}({})",
+            service.name,
+            method.descriptor.get_name(),
+            callback
+        ),
+    ];
+    statements.extend(write_requests(method, request).map(|write| format!("  {}", write)));
+    statements.push("  call.end()".to_string());
+    statements.push("})".to_string());
+    statements.join(&format!("\n{}", indent))
+}
+
+/// The statements of a `subscribe` resolver opening the stream of responses of a method as
+/// `call`, like `method_call`. Bidirectional streaming methods are sent the requests of their
+/// list argument once open.
+fn open_stream(service: &Service, method: &Method, request: &str, indent: &str) -> String {
+    if !method.descriptor.get_client_streaming() {
+        return format!(
+            "const call = {}Stub.{}({})",
+            service.name,
+            method.descriptor.get_name(),
+            request
+        );
+    }
+
+    let mut statements = vec![format!(
+        "const call = {}Stub.{}()",
+        service.name,
+        method.descriptor.get_name()
+    )];
+    statements.extend(write_requests(method, request));
+    statements.join(&format!("\n{}", indent))
+}
+
+/// The statement writing the requests of a client or bidirectional streaming method to its
+/// `call`, unless they are `google.protobuf.Empty`.
+fn write_requests(method: &Method, request: &str) -> Option<String> {
+    method.argument_name.as_ref().map(|_| {
+        format!(
+            "for (const message of {} || []) call.write(message)",
+            request
+        )
+    })
+}
+
+/// Declares the `@oneOf` directive for GraphQL implementations that predate it.
+const ONE_OF_DIRECTIVE: &str = "directive @oneOf on INPUT_OBJECT";
+
+/// The `Query` root type of a schema without queries.
+const EMPTY_QUERY: &str = r#"type Query {
+  """There are no queries, but GraphQL requires a field."""
+  _empty: Boolean
+}"#;
+
+/// Escapes SDL for a JS template literal, where descriptions may hold backslashes, backquotes and
+/// placeholders.
+fn template_literal(sdl: &str) -> String {
+    sdl.replace('\\', r"\\")
+        .replace('`', r"\`")
+        .replace("${", r"\${")
+}
+
+/// A single-quoted JS string literal, for the file names and include paths the resolvers load
+/// the proto files from.
+fn string_literal(value: &str) -> String {
+    format!(
+        "'{}'",
+        value
+            .replace('\\', r"\\")
+            .replace('\'', r"\'")
+            .replace('\n', r"\n")
+    )
+}
+
+fn render<T: ::std::fmt::Display>(value: &T) -> Result<String, ::std::fmt::Error> {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    write!(out, "{}", value)?;
+    Ok(out)
+}
+
+/// Whether the object type and the input type of a message are rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    pub output: bool,
+    pub input: bool,
+}
 
-pub struct GqlTypeDefs {
+impl Usage {
+    pub const ALL: Usage = Usage {
+        output: true,
+        input: true,
+    };
+}
+
+pub struct GqlTypeDefs<'a> {
+    config: &'a Config,
+    /// The service or method, by fully qualified name, each field of the root and service types
+    /// was given to, as `Type.field`.
+    fields: HashMap<String, String>,
     objects: Vec<ObjectType>,
+    /// The usage of each object, by name.
+    usages: HashMap<String, Usage>,
+    /// Objects declared in files that are not part of the output, which the resolvers still need
+    /// to convert.
+    dependency_objects: Vec<ObjectType>,
     enums: Vec<EnumType>,
     services: Vec<Service>,
 }
 
-impl GqlTypeDefs {
-    pub fn new() -> GqlTypeDefs {
+impl<'a> GqlTypeDefs<'a> {
+    pub fn new(config: &'a Config) -> GqlTypeDefs<'a> {
         GqlTypeDefs {
+            config,
+            fields: HashMap::new(),
             objects: Vec::new(),
+            usages: HashMap::new(),
+            dependency_objects: Vec::new(),
             enums: Vec::new(),
             services: Vec::new(),
         }
     }
 
+    /// The messages and enums referenced by the objects and services.
+    pub fn referenced_types(&self) -> Vec<String> {
+        let object_references = self
+            .objects
+            .iter()
+            .flat_map(|object| object.referenced_types());
+        let method_references = self
+            .services
+            .iter()
+            .flat_map(|service| service.methods.iter())
+            .flat_map(|method| {
+                vec![
+                    method.input_type.referenced_type(),
+                    method.output_type.referenced_type(),
+                ]
+            })
+            .flatten();
+
+        object_references
+            .chain(method_references)
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The non-builtin scalars referenced by the fields of the generated types, sorted by name.
+    pub fn custom_scalars(&self) -> Vec<CustomScalar> {
+        let mut custom: Vec<CustomScalar> = Vec::new();
+
+        for field_type in self
+            .objects
+            .iter()
+            .flat_map(|object| object.field_types())
+            .filter(|field_type| field_type.is_scalar())
+        {
+            let name = field_type.named_type();
+            if BUILTIN_SCALARS.contains(&name.as_str()) {
+                continue;
+            }
+            if let Some(scalar) = custom.iter_mut().find(|scalar| scalar.name == name) {
+                // The JSON scalar describes how it holds `Any` messages when it does.
+                if field_type.well_known == Some(WellKnownType::Any) {
+                    scalar.well_known = field_type.well_known;
+                }
+                continue;
+            }
+
+            custom.push(CustomScalar {
+                name,
+                proto_type: field_type.proto_type,
+                // JSON maps share the scalar of the JSON well-known types.
+                well_known: if field_type.is_json_map() {
+                    Some(WellKnownType::Struct)
+                } else {
+                    field_type.well_known
+                },
+            });
+        }
+
+        custom.sort_by(|a, b| a.name.cmp(&b.name));
+        custom
+    }
+
+    fn usage(&self, object: &ObjectType) -> Usage {
+        self.usages[&object.name]
+    }
+
+    /// The oneofs of the rendered object types, unions of which need a type resolver.
+    fn output_oneofs(&self) -> impl Iterator<Item = &Oneof> {
+        self.objects
+            .iter()
+            .filter(move |object| self.usage(object).output)
+            .flat_map(|object| object.oneofs.iter())
+    }
+
+    /// The oneofs of the rendered input types, which need the `@oneOf` directive.
+    fn input_oneofs(&self) -> impl Iterator<Item = &Oneof> {
+        self.objects
+            .iter()
+            .filter(move |object| self.usage(object).input)
+            .flat_map(|object| object.oneofs.iter())
+    }
+
     pub fn push_service(&mut self, service: Service) {
         self.services.push(service)
     }
 
-    pub fn push_object(&mut self, object: ObjectType) {
+    pub fn push_object(&mut self, object: ObjectType, usage: Usage) {
+        self.usages.insert(object.name.clone(), usage);
         self.objects.push(object)
     }
 
+    pub fn push_dependency_object(&mut self, object: ObjectType) {
+        self.dependency_objects.push(object)
+    }
+
     pub fn push_enum(&mut self, enum_: EnumType) {
         self.enums.push(enum_)
     }
 
-    pub fn synthetize_subscription(&self) -> ObjectType {
-        ObjectType {
-            name: "Subscription".to_string(),
-            description: None,
-            fields: self.services
-                .iter()
-                .map(|s| Field {
-                    name: s.name.to_mixed_case(),
-                    description: None,
-                    required: true,
-                    type_: FieldType {
-                        proto_type: FieldDescriptorProto_Type::TYPE_MESSAGE,
-                        type_name: format!("{}ServiceSubscriptions!", s.name),
-                        label: FieldDescriptorProto_Label::LABEL_REQUIRED,
-                    },
+    /// Whether the methods of the operation are grouped by service.
+    fn is_grouped(&self, operation: Operation) -> bool {
+        self.config.root_fields == RootFields::Services && operation != Operation::Subscription
+    }
+
+    /// The field of a method, as `Type.field`.
+    pub fn method_field(&self, service: &Service, method: &Method) -> String {
+        if self.is_grouped(method.operation) {
+            format!("{}.{}", service.type_name(method.operation), method.name)
+        } else {
+            format!("{:?}.{}", method.operation, service.root_field_name(method))
+        }
+    }
+
+    /// The root fields of a service, as `Type.field`, for the operations it has methods of.
+    pub fn service_fields(&self, service: &Service) -> Vec<String> {
+        [Operation::Query, Operation::Mutation]
+            .iter()
+            .filter(|&&operation| {
+                self.is_grouped(operation) && !service.methods_of(operation).is_empty()
+            })
+            .map(|operation| format!("{:?}.{}", operation, service.name.to_mixed_case()))
+            .collect()
+    }
+
+    /// The types holding the queries and the mutations of a service, unless they are root fields.
+    pub fn service_type_names(&self, service: &Service) -> Vec<String> {
+        [Operation::Query, Operation::Mutation]
+            .iter()
+            .filter(|&&operation| {
+                self.is_grouped(operation) && !service.methods_of(operation).is_empty()
+            })
+            .map(|&operation| service.type_name(operation))
+            .collect()
+    }
+
+    /// Gives a field to a service or a method, by its fully qualified name. Fails when another
+    /// one already has it.
+    pub fn claim_field(&mut self, field: String, owner: &str) -> Result<(), ErrorKind> {
+        match self.fields.get(&field) {
+            Some(other) if other != owner => {
+                return Err(ErrorKind::FieldCollision {
+                    field,
+                    first: other.clone(),
+                    second: owner.to_string(),
                 })
-                .collect(),
+            }
+            Some(_) => (),
+            None => {
+                self.fields.insert(field, owner.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn has_methods(&self, operation: Operation) -> bool {
+        self.services
+            .iter()
+            .any(|service| !service.methods_of(operation).is_empty())
+    }
+
+    /// The services rendered as types of their own.
+    fn service_types(&self) -> impl Iterator<Item = &Service> {
+        self.services.iter().filter(move |service| {
+            self.config.root_fields == RootFields::Services && service.has_types()
+        })
+    }
+
+    /// Whether the schema has no queries, but other root types that GraphQL only accepts next to
+    /// a `Query` type with fields.
+    fn has_empty_query(&self) -> bool {
+        !self.has_methods(Operation::Query)
+            && (self.has_methods(Operation::Mutation) || self.has_methods(Operation::Subscription))
+    }
+
+    /// The root types of the schema: the ones with methods, and `Query` when other ones need it.
+    fn roots(&self) -> Vec<Operation> {
+        [
+            Operation::Query,
+            Operation::Mutation,
+            Operation::Subscription,
+        ]
+        .iter()
+        .cloned()
+        .filter(|&operation| {
+            self.has_methods(operation) || operation == Operation::Query && self.has_empty_query()
+        })
+        .collect()
+    }
+
+    /// Renders the root type of an operation, with a field per service or per method.
+    fn render_root(&self, operation: Operation) -> Result<String, ::std::fmt::Error> {
+        if operation == Operation::Query && self.has_empty_query() {
+            Ok(EMPTY_QUERY.to_string())
+        } else if self.is_grouped(operation) {
+            render(&self.synthetize_operation(operation))
+        } else {
+            render(&RootType {
+                operation,
+                services: &self.services,
+            })
         }
     }
 
-    pub fn synthetize_query(&self) -> ObjectType {
+    /// The `Query` or `Mutation` root type, with a field per service that has methods of the
+    /// operation.
+    pub fn synthetize_operation(&self, operation: Operation) -> ObjectType {
         ObjectType {
-            name: "Query".to_string(),
+            name: format!("{:?}", operation),
             description: None,
-            fields: self.services
+            oneofs: Vec::new(),
+            fields: self
+                .services
                 .iter()
+                .filter(|s| !s.methods_of(operation).is_empty())
                 .map(|s| Field {
                     name: s.name.to_mixed_case(),
-                    description: None,
+                    proto_name: s.name.clone(),
+                    description: s.description.clone(),
                     required: true,
+                    deprecation_reason: None,
                     type_: FieldType {
                         proto_type: FieldDescriptorProto_Type::TYPE_MESSAGE,
-                        type_name: format!("{}Service!", s.name),
+                        type_name: s.type_name(operation),
                         label: FieldDescriptorProto_Label::LABEL_REQUIRED,
+                        well_known: None,
+                        map_entry: None,
+                        gql_name: Some(s.type_name(operation)),
+                        unspecified: None,
+                        zero_value: None,
                     },
                 })
                 .collect(),
@@ -75,48 +410,104 @@ impl GqlTypeDefs {
         let mut out = String::new();
         let mut all_exports: Vec<String> = Vec::new();
 
-        for e in self.enums.iter() {
+        for scalar in self.custom_scalars() {
             write!(
                 out,
                 "const {} = `\n{}\n`\n\n",
-                e.name,
-                e.to_string().replace('`', r"\`")
+                scalar.name,
+                template_literal(&scalar.to_string())
             )?;
-            all_exports.push(e.name.to_string());
+            all_exports.push(scalar.name);
         }
 
-        for object in self.objects.iter() {
+        if self.input_oneofs().next().is_some() {
+            write!(out, "const OneOfDirective = `\n{}\n`\n\n", ONE_OF_DIRECTIVE)?;
+            all_exports.push("OneOfDirective".to_string());
+        }
+
+        for e in self.enums.iter() {
             write!(
                 out,
                 "const {} = `\n{}\n`\n\n",
-                object.name,
-                object.to_string().replace('`', r"\`")
-            )?;
-            all_exports.push(object.name.to_string());
-            let input = InputType::from(object.clone());
-            write!(
-                out,
-                "const {}Input = `\n{}\n`\n\n",
-                input.name,
-                input.to_string().replace('`', r"\`")
+                e.name,
+                template_literal(&render(e)?)
             )?;
-            all_exports.push(format!("{}Input", input.name));
+            all_exports.push(e.name.to_string());
         }
 
-        for service in self.services.iter() {
+        for object in self.objects.iter() {
+            let usage = self.usage(object);
+            if usage.output {
+                write!(
+                    out,
+                    "const {} = `\n{}\n`\n\n",
+                    object.name,
+                    template_literal(&render(object)?)
+                )?;
+                all_exports.push(object.name.to_string());
+                for oneof in object.oneofs.iter() {
+                    let name = match oneof.style {
+                        OneofStyle::Fields => oneof.case_enum_name(),
+                        OneofStyle::Union => oneof.type_name.clone(),
+                    };
+                    write!(
+                        out,
+                        "const {} = `\n{}\n`\n\n",
+                        name,
+                        template_literal(&render(oneof)?)
+                    )?;
+                    all_exports.push(name);
+                }
+                for (name, entry) in object.map_entries() {
+                    write!(
+                        out,
+                        "const {} = `\n{}\n`\n\n",
+                        name,
+                        template_literal(&render(&entry.object_type(&name))?)
+                    )?;
+                    all_exports.push(name);
+                }
+            }
+            if !usage.input {
+                continue;
+            }
+            let inputs = ::std::iter::once(InputType::from(object.clone()))
+                .chain(object.oneofs.iter().map(Oneof::input_type))
+                .chain(
+                    object
+                        .map_entries()
+                        .map(|(name, entry)| entry.input_type(&name)),
+                );
+            for input in inputs {
+                write!(
+                    out,
+                    "const {}Input = `\n{}\n`\n\n",
+                    input.name,
+                    template_literal(&render(&input)?)
+                )?;
+                all_exports.push(format!("{}Input", input.name));
+            }
+        }
+
+        for service in self.service_types() {
             write!(
                 out,
                 "const {} = `\n{}\n`\n\n",
                 service.name,
-                service.to_string().replace('`', r"\`")
+                template_literal(&render(service)?)
             )?;
             all_exports.push(service.name.to_string());
         }
 
-        let query = self.synthetize_query();
-        write!(out, "const {} = `\n{}\n`\n\n", query.name, query)?;
-        let subscription = self.synthetize_subscription();
-        write!(out, "const {} = `\n{}\n`\n\n", subscription.name, subscription)?;
+        let roots = self.roots();
+        for &operation in roots.iter() {
+            write!(
+                out,
+                "const {:?} = `\n{}\n`\n\n",
+                operation,
+                template_literal(&self.render_root(operation)?)
+            )?;
+        }
 
         // write!(out, "const typeDefsWithoutQuery = [\n")?;
         // for export in all_exports.iter() {
@@ -128,7 +519,9 @@ impl GqlTypeDefs {
         for export in all_exports.iter() {
             write!(out, "  {},\n", export)?;
         }
-        write!(out, "  Query,\n")?;
+        for operation in roots {
+            write!(out, "  {:?},\n", operation)?;
+        }
         write!(out, "]\n")?;
 
         Ok(out)
@@ -137,109 +530,320 @@ impl GqlTypeDefs {
     pub fn render_resolvers(&self) -> Result<String, ::std::fmt::Error> {
         use std::fmt::Write;
 
-        let proto_file_names: ::std::collections::HashSet<String> = self.services
+        let proto_file_names: ::std::collections::BTreeSet<String> = self
+            .services
             .iter()
             .map(|service| service.origin_file_name.clone())
             .collect();
 
-        let mut out = String::new();
-        write!(out, "const grpc = require('grpc')\n")?;
+        let scalars: Vec<(CustomScalar, JsScalarImplementation)> = self
+            .custom_scalars()
+            .into_iter()
+            .filter_map(|scalar| {
+                scalar
+                    .js_implementation()
+                    .map(|implementation| (scalar, implementation))
+            })
+            .collect();
 
-        for proto_file_name in proto_file_names.iter() {
-            write!(
+        let codec_objects: Vec<ObjectType> = self
+            .objects
+            .iter()
+            .chain(self.dependency_objects.iter())
+            .cloned()
+            .collect();
+
+        let mut out = String::new();
+        match self.config.grpc {
+            Grpc::GrpcJs => {
+                writeln!(out, "const grpc = require('@grpc/grpc-js')")?;
+                writeln!(out, "const protoLoader = require('@grpc/proto-loader')")?;
+            }
+            Grpc::Legacy => write!(out, "const grpc = require('grpc')\n")?,
+        }
+        if !scalars.is_empty() {
+            writeln!(
                 out,
-                "const {} = grpc.load('./{}')\n\n",
-                proto_file_name.to_camel_case().replace(".proto", ""),
-                proto_file_name
+                "const {{ GraphQLScalarType, Kind }} = require('graphql')"
             )?;
         }
+        if scalars.iter().any(|(scalar, _)| scalar.is_integer()) {
+            write!(out, "\n{}", JS_PARSE_INTEGER)?;
+        }
+        if codecs::uses_well_known_types(&codec_objects)
+            || self
+                .services
+                .iter()
+                .flat_map(|service| service.methods.iter())
+                .any(|method| {
+                    method.input_type.well_known.is_some()
+                        || method.output_type.well_known.is_some()
+                })
+        {
+            write!(out, "\n{}", codecs::JS_WELL_KNOWN_TYPES)?;
+        }
+        if codecs::uses_maps(&codec_objects) {
+            write!(out, "\n{}", codecs::JS_MAPS)?;
+        }
+        if self.has_methods(Operation::Subscription) {
+            write!(out, "\n{}", streams::JS_SUBSCRIPTIONS)?;
+        }
+        writeln!(out)?;
+
+        match self.config.grpc {
+            Grpc::GrpcJs if !self.services.is_empty() => {
+                // The codecs read and write the proto field names, 64 bit integers as strings,
+                // enum values by name and the set member of oneofs. Unset fields are left out,
+                // so that the ones with presence read as null.
+                writeln!(
+                    out,
+                    "const proto = grpc.loadPackageDefinition(protoLoader.loadSync(["
+                )?;
+                for proto_file_name in proto_file_names.iter() {
+                    writeln!(out, "  {},", string_literal(proto_file_name))?;
+                }
+                writeln!(out, "], {{")?;
+                writeln!(out, "  keepCase: true,")?;
+                writeln!(out, "  longs: String,")?;
+                writeln!(out, "  enums: String,")?;
+                writeln!(out, "  defaults: false,")?;
+                writeln!(out, "  oneofs: true,")?;
+                if !self.config.include_paths.is_empty() {
+                    let include_paths: Vec<String> = self
+                        .config
+                        .include_paths
+                        .iter()
+                        .map(|path| string_literal(path))
+                        .collect();
+                    writeln!(out, "  includeDirs: [{}],", include_paths.join(", "))?;
+                }
+                write!(out, "}}))\n\n")?;
+            }
+            Grpc::GrpcJs => (),
+            Grpc::Legacy => {
+                for proto_file_name in proto_file_names.iter() {
+                    write!(
+                        out,
+                        "const {} = grpc.load({})\n\n",
+                        proto_file_name.to_camel_case().replace(".proto", ""),
+                        string_literal(&format!("./{}", proto_file_name))
+                    )?;
+                }
+            }
+        }
 
         for service in self.services.iter() {
+            // grpc-js loads the services under their package, `grpc.load` under their file.
+            let constructor = match self.config.grpc {
+                Grpc::GrpcJs => ["proto", &service.package, &service.proto_name]
+                    .iter()
+                    .filter(|segment| !segment.is_empty())
+                    .cloned()
+                    .collect::<Vec<&str>>()
+                    .join("."),
+                Grpc::Legacy => format!(
+                    "{}.{}",
+                    service
+                        .origin_file_name
+                        .to_camel_case()
+                        .replace(".proto", ""),
+                    service.proto_name
+                ),
+            };
             write!(
                 out,
-                "const {}Stub = new {}.{}(process.env.{}_BACKEND_URL, grpc.credentials.createInsecure())\n\n",
+                "const {}Stub = new {}(process.env.{}_BACKEND_URL, grpc.credentials.createInsecure())\n\n",
                 service.name,
-                service.origin_file_name.to_camel_case().replace(".proto", ""),
-                 service.name, service.name.TO_SHOUTY_SNEK_CASE()
+                constructor,
+                service.proto_name.TO_SHOUTY_SNEK_CASE()
             )?;
         }
 
-        write!(out, "module.exports = {{\n  Query: {{\n")?;
-
-        for service in self.services.iter() {
-            write!(out, "    {}: () => ({{\n", service.name.to_mixed_case())?;
-            for method in service.methods.iter().filter(|m| !m.has_server_streaming()) {
-                write!(
+        for (scalar, implementation) in scalars.iter() {
+            writeln!(
+                out,
+                "const parse{}Value = {}\n",
+                scalar.name, implementation.parse_value
+            )?;
+            if let Some(parse_literal) = implementation.parse_literal {
+                writeln!(
                     out,
-                    "      {}: ({{ {}: req }}) => {{
-        return new Promise((resolve, reject) => {}Stub.{}({{...req}}, (err, res) => err ? reject(err) : resolve(res)))
-      }},\n",
-                    method.get_name().to_mixed_case(),
-                    method.get_input_type().to_snake_case(),
-                    service.name,
-                    method.get_name(),
+                    "const parse{}Literal = {}\n",
+                    scalar.name, parse_literal
                 )?;
             }
-            write!(out, "    }}),\n")?;
         }
 
-        write!(out, "  }},\n")?;
-        write!(out, "  Subscription: {{\n")?;
+        codecs::render_codecs(&codec_objects, &mut out)?;
 
-        for service in self.services.iter() {
-            let subscriptions: Vec<_> = service.methods.iter().filter(|m| m.has_server_streaming()).collect();
-            if subscriptions.is_empty() {
-                continue
+        writeln!(out, "module.exports = {{")?;
+
+        let roots = self.roots();
+        for &operation in roots.iter() {
+            if operation == Operation::Subscription {
+                continue;
             }
 
-            write!(out, "    {}: () => ({{", service.name.to_mixed_case())?;
+            writeln!(out, "  {:?}: {{", operation)?;
+            for service in self.services.iter() {
+                let methods = service.methods_of(operation);
+                if methods.is_empty() {
+                    continue;
+                }
+
+                if self.is_grouped(operation) {
+                    writeln!(out, "    {}: () => ({{", service.name.to_mixed_case())?;
+                    for method in methods {
+                        let (arguments, request) = request_arguments(method);
+                        write!(
+                            out,
+                            "      {}: ({}) => {{\n        {}\n      }},\n",
+                            method.name,
+                            arguments,
+                            method_call(service, method, &request, "        "),
+                        )?;
+                    }
+                    writeln!(out, "    }}),")?;
+                } else {
+                    for method in methods {
+                        let (arguments, request) = request_arguments(method);
+                        write!(
+                            out,
+                            "    {}: (parent, {}) => {{\n      {}\n    }},\n",
+                            service.root_field_name(method),
+                            arguments,
+                            method_call(service, method, &request, "      "),
+                        )?;
+                    }
+                }
+            }
+            writeln!(out, "  }},")?;
+        }
 
-            for subscription in subscriptions {
-                write!(
-                    out,
-                    "
-        {}: (parent, {{ {}: req }}, {{ pubsub }}) => {{
-          const call = {}Stub.{}({{...req}})
-          // taken from the graphql-yoga example
-          // https://github.com/graphcool/graphql-yoga/blob/master/examples/subscriptions/index.jss
-          const channel = Math.random().toString(36).substring(2, 15) // random channel name
-          call.on('data', data => pubsub.publish(channel, data))
-          call.on('end', () => true)
-          call.on('status', () => true)
-          return pubsub.asyncIterator(channel)
-        }},", subscription.get_name().to_mixed_case(), subscription.get_input_type().to_snake_case(), service.name, subscription.get_name())?;
+        // Subscriptions are root fields, whose `subscribe` resolver iterates over the responses.
+        if self.has_methods(Operation::Subscription) {
+            writeln!(out, "  Subscription: {{")?;
+            for service in self.services.iter() {
+                for method in service.methods_of(Operation::Subscription) {
+                    let (arguments, request) = request_arguments(method);
+                    write!(
+                        out,
+                        "    {}: {{
+      subscribe: (parent, {}) => {{
+        {}
+        return subscribeTo(call, data => {})
+      }},
+      resolve: resolveResponse,
+    }},\n",
+                        service.root_field_name(method),
+                        arguments,
+                        open_stream(service, method, &request, "        "),
+                        codecs::convert(&method.output_type, Direction::FromGrpc, "data"),
+                    )?;
+                }
             }
+            writeln!(out, "  }},")?;
+        }
 
-            write!(out, "\n    }}),\n")?;
+        for oneof in self
+            .output_oneofs()
+            .filter(|oneof| oneof.style == OneofStyle::Union)
+        {
+            writeln!(
+                out,
+                "  {}: {{\n    __resolveType: value => value.__typename,\n  }},",
+                oneof.type_name
+            )?;
+        }
+
+        // The values of renamed enums resolve to the proto ones the grpc stubs know.
+        for enum_ in self.enums.iter().filter(|enum_| enum_.is_renamed()) {
+            writeln!(out, "  {}: {{", enum_.name)?;
+            for value in enum_.values.iter() {
+                writeln!(out, "    {}: '{}',", value.name, value.proto_name)?;
+            }
+            writeln!(out, "  }},")?;
+        }
+
+        for (scalar, implementation) in scalars {
+            let parse_literal = match implementation.parse_literal {
+                Some(_) => format!("parse{}Literal", scalar.name),
+                None => format!(
+                    "ast => {} ? parse{}Value(ast.value) : undefined",
+                    implementation
+                        .literal_kinds
+                        .iter()
+                        .map(|kind| format!("ast.kind === Kind.{}", kind))
+                        .collect::<Vec<_>>()
+                        .join(" || "),
+                    scalar.name
+                ),
+            };
+            write!(
+                out,
+                "  {name}: new GraphQLScalarType({{
+    name: '{name}',
+    serialize: {serialize},
+    parseValue: parse{name}Value,
+    parseLiteral: {parse_literal},
+  }}),\n",
+                name = scalar.name,
+                serialize = implementation.serialize,
+                parse_literal = parse_literal,
+            )?;
         }
 
-        write!(out, "  }},\n}}")?;
+        write!(out, "}}")?;
 
         Ok(out)
     }
 }
 
-impl ::std::fmt::Display for GqlTypeDefs {
+impl<'a> ::std::fmt::Display for GqlTypeDefs<'a> {
     fn fmt(&self, formatter: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        for scalar in self.custom_scalars() {
+            write!(formatter, "{}\n\n", scalar)?;
+        }
+
+        if self.input_oneofs().next().is_some() {
+            write!(formatter, "{}\n\n", ONE_OF_DIRECTIVE)?;
+        }
+
         for e in self.enums.iter() {
             write!(formatter, "{}\n\n", e)?;
         }
 
         for object in self.objects.iter() {
-            write!(formatter, "{}\n\n", object)?;
-            // TODO: Generate only the input types required by the generated services
-            write!(formatter, "{}\n\n", InputType::from((*object).clone()))?;
+            let usage = self.usage(object);
+            if usage.output {
+                write!(formatter, "{}\n\n", object)?;
+                for oneof in object.oneofs.iter() {
+                    write!(formatter, "{}\n\n", oneof)?;
+                }
+                for (name, entry) in object.map_entries() {
+                    write!(formatter, "{}\n\n", entry.object_type(&name))?;
+                }
+            }
+            if usage.input {
+                write!(formatter, "{}\n\n", InputType::from((*object).clone()))?;
+                for oneof in object.oneofs.iter() {
+                    write!(formatter, "{}\n\n", oneof.input_type())?;
+                }
+                for (name, entry) in object.map_entries() {
+                    write!(formatter, "{}\n\n", entry.input_type(&name))?;
+                }
+            }
         }
 
-        for service in self.services.iter() {
+        for service in self.service_types() {
             write!(formatter, "{}\n\n", service)?;
         }
 
-        if self.services.len() > 0 {
-            let query = self.synthetize_query();
-            write!(formatter, "{}", query)?;
-        }
-
-        Ok(())
+        let roots = self
+            .roots()
+            .into_iter()
+            .map(|operation| self.render_root(operation))
+            .collect::<Result<Vec<String>, _>>()?;
+        write!(formatter, "{}", roots.join("\n\n"))
     }
 }
@@ -0,0 +1,91 @@
+use protobuf::descriptor::FieldDescriptorProto_Type;
+
+/// The types from `google/protobuf/*.proto` that get a GraphQL representation of their own
+/// instead of being rendered as objects.
+///
+/// See https://developers.google.com/protocol-buffers/docs/proto3#json for the canonical JSON
+/// encoding they follow.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WellKnownType {
+    /// An RFC 3339 `DateTime` string
+    Timestamp,
+    /// A `Duration` string with an `s` suffix, like `"1.5s"`
+    Duration,
+    /// `Boolean` when it is a field or method result, no argument when it is a method input
+    Empty,
+    /// The list of paths, as `[String!]`
+    FieldMask,
+    Struct,
+    Value,
+    ListValue,
+    /// A JSON object with the type URL under `@type` and the base64-encoded message under `value`
+    Any,
+    /// `StringValue`, `Int32Value`... are rendered as the nullable scalar of the proto type they
+    /// wrap.
+    Wrapper(FieldDescriptorProto_Type),
+}
+
+impl WellKnownType {
+    /// Takes a fully qualified type name, as found in field and method descriptors.
+    pub fn from_type_name(type_name: &str) -> Option<WellKnownType> {
+        use self::FieldDescriptorProto_Type::*;
+
+        let well_known_type = match type_name {
+            ".google.protobuf.Timestamp" => WellKnownType::Timestamp,
+            ".google.protobuf.Duration" => WellKnownType::Duration,
+            ".google.protobuf.Empty" => WellKnownType::Empty,
+            ".google.protobuf.FieldMask" => WellKnownType::FieldMask,
+            ".google.protobuf.Struct" => WellKnownType::Struct,
+            ".google.protobuf.Value" => WellKnownType::Value,
+            ".google.protobuf.ListValue" => WellKnownType::ListValue,
+            ".google.protobuf.Any" => WellKnownType::Any,
+            ".google.protobuf.DoubleValue" => WellKnownType::Wrapper(TYPE_DOUBLE),
+            ".google.protobuf.FloatValue" => WellKnownType::Wrapper(TYPE_FLOAT),
+            ".google.protobuf.Int64Value" => WellKnownType::Wrapper(TYPE_INT64),
+            ".google.protobuf.UInt64Value" => WellKnownType::Wrapper(TYPE_UINT64),
+            ".google.protobuf.Int32Value" => WellKnownType::Wrapper(TYPE_INT32),
+            ".google.protobuf.UInt32Value" => WellKnownType::Wrapper(TYPE_UINT32),
+            ".google.protobuf.BoolValue" => WellKnownType::Wrapper(TYPE_BOOL),
+            ".google.protobuf.StringValue" => WellKnownType::Wrapper(TYPE_STRING),
+            ".google.protobuf.BytesValue" => WellKnownType::Wrapper(TYPE_BYTES),
+            _ => return None,
+        };
+
+        Some(well_known_type)
+    }
+
+    /// The named GraphQL type that represents it. For field masks, this is the item type.
+    ///
+    /// Wrappers are represented by the configured scalar for the type they wrap, so they have
+    /// none of their own.
+    pub fn gql_type(&self) -> Option<&'static str> {
+        let gql_type = match *self {
+            WellKnownType::Timestamp => "DateTime",
+            WellKnownType::Duration => "Duration",
+            WellKnownType::Empty => "Boolean",
+            WellKnownType::FieldMask => "String",
+            WellKnownType::Struct
+            | WellKnownType::Value
+            | WellKnownType::ListValue
+            | WellKnownType::Any => "JSON",
+            WellKnownType::Wrapper(_) => return None,
+        };
+
+        Some(gql_type)
+    }
+
+    /// The suffix of the conversion functions in the generated `wellKnownTypes` JS helpers.
+    pub fn js_name(&self) -> &'static str {
+        match *self {
+            WellKnownType::Timestamp => "Timestamp",
+            WellKnownType::Duration => "Duration",
+            WellKnownType::Empty => "Empty",
+            WellKnownType::FieldMask => "FieldMask",
+            WellKnownType::Struct => "Struct",
+            WellKnownType::Value => "Value",
+            WellKnownType::ListValue => "ListValue",
+            WellKnownType::Any => "Any",
+            WellKnownType::Wrapper(_) => "Wrapper",
+        }
+    }
+}
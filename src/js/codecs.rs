@@ -0,0 +1,384 @@
+//! Conversions between the values the grpc stubs produce and accept, and the values in the
+//! GraphQL schema.
+//!
+//! The generated resolvers module contains a `fromGrpc` and a `toGrpc` function for each object
+//! type, which convert whole messages field by field.
+
+use config::{MapStyle, OneofStyle};
+use gql::{Field, FieldType, ObjectType, Oneof};
+use protobuf::descriptor::FieldDescriptorProto_Type;
+use std::fmt::Write;
+
+/// Conversions between the canonical JSON representation of the well-known types and their
+/// message shape.
+pub const JS_WELL_KNOWN_TYPES: &str = r#"const wellKnownTypes = {
+  fromTimestamp: timestamp => {
+    if (timestamp == null) return null
+    const nanos = timestamp.nanos || 0
+    const date = new Date(Number(timestamp.seconds || 0) * 1000).toISOString().replace(/\.\d+Z$/, '')
+    return nanos ? `${date}.${String(nanos).padStart(9, '0').replace(/0+$/, '')}Z` : `${date}Z`
+  },
+  toTimestamp: dateTime => {
+    if (dateTime == null) return null
+    const [, date, fraction = '', offset] = /^(.+?)(?:\.(\d{1,9}))?(Z|[+-]\d\d:\d\d)$/i.exec(dateTime)
+    return {
+      seconds: String(Math.floor(Date.parse(date + offset) / 1000)),
+      nanos: Number(fraction.padEnd(9, '0')),
+    }
+  },
+  fromDuration: duration => {
+    if (duration == null) return null
+    const seconds = BigInt(String(duration.seconds || 0))
+    const nanos = duration.nanos || 0
+    const sign = seconds < 0 || nanos < 0 ? '-' : ''
+    const fraction = nanos ? `.${String(Math.abs(nanos)).padStart(9, '0').replace(/0+$/, '')}` : ''
+    return `${sign}${seconds < 0 ? -seconds : seconds}${fraction}s`
+  },
+  toDuration: duration => {
+    if (duration == null) return null
+    const [, sign, seconds, fraction = ''] = /^(-?)(\d+)(?:\.(\d{1,9}))?s$/.exec(duration)
+    const nanos = Number(fraction.padEnd(9, '0'))
+    return { seconds: sign + seconds, nanos: sign ? -nanos : nanos }
+  },
+  fromEmpty: () => true,
+  toEmpty: () => ({}),
+  fromFieldMask: fieldMask => fieldMask == null ? null : fieldMask.paths || [],
+  toFieldMask: paths => paths == null ? null : { paths },
+  fromStruct: struct => {
+    if (struct == null) return null
+    const object = {}
+    Object.keys(struct.fields || {}).forEach(key => {
+      object[key] = wellKnownTypes.fromValue(struct.fields[key])
+    })
+    return object
+  },
+  toStruct: object => {
+    if (object == null) return null
+    const fields = {}
+    Object.keys(object).forEach(key => {
+      fields[key] = wellKnownTypes.toValue(object[key])
+    })
+    return { fields }
+  },
+  fromValue: value => {
+    if (value == null) return null
+    switch (value.kind || Object.keys(value).find(key => value[key] != null)) {
+      case 'numberValue': return value.numberValue
+      case 'stringValue': return value.stringValue
+      case 'boolValue': return value.boolValue
+      case 'structValue': return wellKnownTypes.fromStruct(value.structValue)
+      case 'listValue': return wellKnownTypes.fromListValue(value.listValue)
+      default: return null
+    }
+  },
+  toValue: json => {
+    if (json === undefined) return undefined
+    if (json === null) return { nullValue: 'NULL_VALUE' }
+    if (Array.isArray(json)) return { listValue: wellKnownTypes.toListValue(json) }
+    switch (typeof json) {
+      case 'number': return { numberValue: json }
+      case 'string': return { stringValue: json }
+      case 'boolean': return { boolValue: json }
+      default: return { structValue: wellKnownTypes.toStruct(json) }
+    }
+  },
+  fromListValue: listValue => listValue == null ? null : (listValue.values || []).map(wellKnownTypes.fromValue),
+  toListValue: values => values == null ? null : { values: values.map(wellKnownTypes.toValue) },
+  fromAny: any => any == null ? null : {
+    '@type': any.type_url,
+    value: Buffer.from(any.value || []).toString('base64'),
+  },
+  toAny: json => json == null ? null : {
+    type_url: json['@type'],
+    value: Buffer.from(json.value || '', 'base64'),
+  },
+  fromWrapper: wrapper => wrapper == null ? null : wrapper.value,
+  toWrapper: value => value == null ? null : { value },
+}
+"#;
+
+/// Conversions of map fields, which grpc hands out as objects keyed by the map keys, or as
+/// `Map`-like objects with a `forEach` method.
+pub const JS_MAPS: &str = r#"const maps = {
+  forEach: (map, callback) => typeof map.forEach === 'function'
+    ? map.forEach(callback)
+    : Object.keys(map).forEach(key => callback(map[key], key)),
+  toEntries: (map, convertKey, convertValue) => {
+    if (map == null) return []
+    const entries = []
+    maps.forEach(map, (value, key) => entries.push({ key: convertKey(key), value: convertValue(value) }))
+    return entries
+  },
+  fromEntries: (entries, convertValue) => {
+    if (entries == null) return null
+    const map = {}
+    entries.forEach(({ key, value }) => { map[key] = convertValue(value) })
+    return map
+  },
+  toObject: (map, convertValue) => {
+    if (map == null) return null
+    const object = {}
+    maps.forEach(map, (value, key) => { object[key] = convertValue(value) })
+    return object
+  },
+}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    FromGrpc,
+    ToGrpc,
+}
+
+impl Direction {
+    fn prefix(&self) -> &'static str {
+        match *self {
+            Direction::FromGrpc => "from",
+            Direction::ToGrpc => "to",
+        }
+    }
+}
+
+/// The JS function converting a single value of the given type, `None` if values are passed
+/// through as they are.
+fn converter(field_type: &FieldType, direction: Direction) -> Option<String> {
+    if let Some(well_known) = field_type.well_known {
+        return Some(format!(
+            "wellKnownTypes.{}{}",
+            direction.prefix(),
+            well_known.js_name()
+        ));
+    }
+
+    if field_type.is_object() {
+        return Some(format!(
+            "{}Grpc.{}",
+            direction.prefix(),
+            field_type.named_type()
+        ));
+    }
+
+    // The zero value left out of the enum is null, and null is the zero value for grpc.
+    match (field_type.unspecified.as_ref(), direction) {
+        (Some(unspecified), Direction::FromGrpc) => Some(format!(
+            "(value => value === '{}' ? null : value)",
+            unspecified
+        )),
+        _ => None,
+    }
+}
+
+/// Like `converter`, with the identity function for values passed through.
+fn converter_or_identity(field_type: &FieldType, direction: Direction) -> String {
+    converter(field_type, direction).unwrap_or_else(|| "value => value".to_string())
+}
+
+/// A JS expression converting the value of `expression`, which has the given type.
+pub fn convert(field_type: &FieldType, direction: Direction, expression: &str) -> String {
+    if let Some(ref map_entry) = field_type.map_entry {
+        let value = converter_or_identity(&map_entry.value, direction);
+        return match (map_entry.style, direction) {
+            (MapStyle::Json, _) => format!("maps.toObject({}, {})", expression, value),
+            (MapStyle::Entries, Direction::ToGrpc) => {
+                format!("maps.fromEntries({}, {})", expression, value)
+            }
+            (MapStyle::Entries, Direction::FromGrpc) => {
+                // Keys are strings in objects, which would all be true as booleans.
+                let key = if map_entry.key.proto_type == FieldDescriptorProto_Type::TYPE_BOOL {
+                    "key => key === true || key === 'true'".to_string()
+                } else {
+                    converter_or_identity(&map_entry.key, direction)
+                };
+                format!("maps.toEntries({}, {}, {})", expression, key, value)
+            }
+        };
+    }
+
+    // grpc leaves empty lists out, and lists of enums have no null items, so they leave out the
+    // zero value instead.
+    match (field_type.unspecified.as_ref(), direction) {
+        (Some(unspecified), Direction::FromGrpc) if field_type.is_repeated() => {
+            return format!(
+                "({} || []).filter(value => value !== '{}')",
+                expression, unspecified
+            );
+        }
+        _ => {}
+    }
+
+    match (converter(field_type, direction), direction) {
+        (Some(converter), Direction::FromGrpc) if field_type.is_repeated() => {
+            format!("({} || []).map({})", expression, converter)
+        }
+        (Some(ref converter), Direction::ToGrpc) if field_type.is_repeated() => format!(
+            "{expression} && {expression}.map({converter})",
+            expression = expression,
+            converter = converter
+        ),
+        (Some(converter), _) => format!("{}({})", converter, expression),
+        (None, Direction::FromGrpc) if field_type.is_repeated() => {
+            format!("{} || []", expression)
+        }
+        (None, _) => expression.to_string(),
+    }
+}
+
+/// The value of a field without presence which grpc leaves out of the messages it hands out
+/// when the field is unset, since the proto files are loaded without `defaults`. The lists and
+/// the map entries are taken care of by their conversions, and unset messages are null.
+fn unset_value(field_type: &FieldType) -> Option<String> {
+    use protobuf::descriptor::FieldDescriptorProto_Type::*;
+
+    if field_type.is_json_map() {
+        return Some("{}".to_string());
+    }
+    if field_type.is_repeated() || field_type.is_message() {
+        return None;
+    }
+
+    let value = match field_type.proto_type {
+        TYPE_ENUM => format!("'{}'", field_type.zero_value.as_ref()?),
+        TYPE_STRING => "''".to_string(),
+        TYPE_BOOL => "false".to_string(),
+        TYPE_BYTES => "Buffer.alloc(0)".to_string(),
+        // 64 bit integers are loaded as strings.
+        TYPE_INT64 | TYPE_UINT64 | TYPE_SINT64 | TYPE_FIXED64 | TYPE_SFIXED64 => "'0'".to_string(),
+        _ => "0".to_string(),
+    };
+    Some(value)
+}
+
+/// Whether any of the fields need the `wellKnownTypes` helpers.
+pub fn uses_well_known_types(objects: &[ObjectType]) -> bool {
+    objects
+        .iter()
+        .flat_map(|object| object.all_fields())
+        .any(|field| {
+            field.type_.well_known.is_some()
+                || field
+                    .type_
+                    .map_entry
+                    .as_ref()
+                    .is_some_and(|map_entry| map_entry.value.well_known.is_some())
+        })
+}
+
+/// Whether any of the fields need the `maps` helpers.
+pub fn uses_maps(objects: &[ObjectType]) -> bool {
+    objects
+        .iter()
+        .flat_map(|object| object.all_fields())
+        .any(|field| field.type_.map_entry.is_some())
+}
+
+/// A JS expression reading the oneof from `message`, checking which member is set in turn.
+fn oneof_from_grpc(oneof: &Oneof) -> String {
+    let mut expression = String::new();
+    for member in oneof.fields.iter() {
+        let value = match oneof.style {
+            OneofStyle::Fields => format!("'{}'", Oneof::case_name(member)),
+            OneofStyle::Union => format!(
+                "{{ __typename: '{}', {}: {} }}",
+                oneof.member_type_name(member),
+                member.name,
+                convert(
+                    &member.type_,
+                    Direction::FromGrpc,
+                    &format!("message.{}", member.proto_name)
+                )
+            ),
+        };
+        expression.push_str(&format!(
+            "message.{} != null ? {} : ",
+            member.proto_name, value
+        ));
+    }
+    expression.push_str("null");
+    expression
+}
+
+/// Renders the `toGrpc` function of an object or a oneof input type.
+fn render_to_grpc(
+    name: &str,
+    fields: &[Field],
+    oneofs: &[Oneof],
+    out: &mut String,
+) -> Result<(), ::std::fmt::Error> {
+    writeln!(out, "  {}: input => input == null ? null : ({{", name)?;
+    for field in fields.iter() {
+        writeln!(
+            out,
+            "    {}: {},",
+            field.proto_name,
+            convert(
+                &field.type_,
+                Direction::ToGrpc,
+                &format!("input.{}", field.name)
+            )
+        )?;
+    }
+    // The oneof input objects hold the members under their proto names, ready to be merged.
+    for oneof in oneofs.iter() {
+        writeln!(
+            out,
+            "    ...toGrpc.{}(input.{}),",
+            oneof.type_name, oneof.name
+        )?;
+    }
+    writeln!(out, "  }}),")
+}
+
+/// Renders the `fromGrpc` and `toGrpc` objects, holding the conversion functions for each
+/// object type.
+pub fn render_codecs(objects: &[ObjectType], out: &mut String) -> Result<(), ::std::fmt::Error> {
+    writeln!(out, "const fromGrpc = {{")?;
+    for object in objects.iter() {
+        writeln!(
+            out,
+            "  {}: message => message == null ? null : ({{",
+            object.name
+        )?;
+        for field in object.fields.iter() {
+            // Fields with presence are null when unset, the others read as their zero value.
+            let value = format!("message.{}", field.proto_name);
+            let value = match unset_value(&field.type_) {
+                Some(ref unset) if field.required => format!("{} ?? {}", value, unset),
+                _ => value,
+            };
+            writeln!(
+                out,
+                "    {}: {},",
+                field.name,
+                convert(&field.type_, Direction::FromGrpc, &value)
+            )?;
+        }
+        for oneof in object.oneofs.iter() {
+            if oneof.style == OneofStyle::Fields {
+                for member in oneof.fields.iter() {
+                    writeln!(
+                        out,
+                        "    {}: {},",
+                        member.name,
+                        convert(
+                            &member.type_,
+                            Direction::FromGrpc,
+                            &format!("message.{}", member.proto_name)
+                        )
+                    )?;
+                }
+            }
+            writeln!(out, "    {}: {},", oneof.name, oneof_from_grpc(oneof))?;
+        }
+        writeln!(out, "  }}),")?;
+    }
+    writeln!(out, "}}\n")?;
+
+    writeln!(out, "const toGrpc = {{")?;
+    for object in objects.iter() {
+        render_to_grpc(&object.name, &object.fields, &object.oneofs, out)?;
+        for oneof in object.oneofs.iter() {
+            render_to_grpc(&oneof.type_name, &oneof.fields, &[], out)?;
+        }
+    }
+    writeln!(out, "}}\n")
+}
@@ -1,12 +1,12 @@
 use gql::GqlTypeDefs;
 use std::fmt::Write;
 
-pub struct ServerModule {
+pub struct ServerModule<'a> {
     package_name: String,
-    type_defs: GqlTypeDefs,
+    type_defs: GqlTypeDefs<'a>,
 }
 
-impl ServerModule {
+impl<'a> ServerModule<'a> {
     fn print(&self) -> Result<String, ::std::fmt::Error> {
         let mut out = String::new();
 
@@ -1,5 +1,4 @@
+pub mod codecs;
 mod gen_server;
 mod resolvers;
-
-use self::gen_server::*;
-use self::resolvers::*;
+pub mod streams;
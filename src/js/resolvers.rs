@@ -1,5 +1,4 @@
 /// import * as
-
 ///
 /// export const resolvers = {
 ///   Query: {
@@ -11,5 +10,4 @@
 /// }
 /// }
 /// }
-
 pub struct Resolver;
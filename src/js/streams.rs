@@ -0,0 +1,55 @@
+//! The JS helpers of the `subscribe` resolvers, which turn the streaming calls of the grpc stubs
+//! into the async iterators GraphQL subscriptions read.
+
+/// An async iterator over the converted responses of a streaming call. Ending the subscription
+/// with `return()` cancels the call, the end of the call ends the subscription, and the error of a
+/// call ending with a status other than OK is its last value, which `resolveResponse` throws so
+/// that it reaches the client as a GraphQL error.
+pub const JS_SUBSCRIPTIONS: &str = r#"const subscribeTo = (call, convert) => {
+  const responses = []
+  const waiting = []
+  let finished = false
+  const push = response => {
+    if (waiting.length > 0) waiting.shift()({ value: response, done: false })
+    else responses.push(response)
+  }
+  const finish = () => {
+    finished = true
+    while (waiting.length > 0) waiting.shift()({ value: undefined, done: true })
+  }
+  call.on('data', data => {
+    if (!finished) push(convert(data))
+  })
+  call.on('error', err => {
+    if (finished) return
+    push(err)
+    finish()
+  })
+  call.on('end', finish)
+  const stop = () => {
+    if (!finished) {
+      responses.length = 0
+      finish()
+      call.cancel()
+    }
+    return Promise.resolve({ value: undefined, done: true })
+  }
+  return {
+    next: () => {
+      if (responses.length > 0) return Promise.resolve({ value: responses.shift(), done: false })
+      if (finished) return Promise.resolve({ value: undefined, done: true })
+      return new Promise(resolve => waiting.push(resolve))
+    },
+    return: stop,
+    throw: err => stop().then(() => Promise.reject(err)),
+    [Symbol.asyncIterator]() {
+      return this
+    },
+  }
+}
+
+const resolveResponse = response => {
+  if (response instanceof Error) throw response
+  return response
+}
+"#;
@@ -1,216 +1,1220 @@
+// The renderers spell out the line breaks of the generated code.
+#![allow(clippy::write_with_newline)]
+
 extern crate heck;
 extern crate protobuf;
-extern crate regex;
 
-// #[cfg(test)]
-// mod gen_tests;
+pub mod config;
+pub mod error;
+#[cfg(test)]
+mod gen_tests;
 mod gql;
+#[allow(dead_code)]
 mod js;
+mod naming;
+mod options;
+mod source_info;
 mod support;
 
+use config::*;
+use error::*;
 use gql::*;
+use naming::TypeNames;
+use options::{GraphqlOptions, Nullability};
+use source_info::{Path, SourceInfo};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
 
 use heck::*;
 
-use protobuf::code_writer::CodeWriter;
-use protobuf::compiler_plugin;
 use protobuf::compiler_plugin::GenResult;
 use protobuf::descriptor::*;
+use protobuf::plugin::*;
+use protobuf::Message;
+
+/// What walking the descriptors needs besides the descriptors being walked.
+struct Context<'a> {
+    config: &'a Config,
+    /// Every message and enum protoc passed, including the ones of the dependencies.
+    declarations: HashMap<String, Declaration<'a>>,
+    /// The source info of every file protoc passed, by file name.
+    source_infos: HashMap<&'a str, SourceInfo<'a>>,
+    type_names: TypeNames,
+    /// The types the generated services use, unless all of them are rendered.
+    reachable: Option<Reachable>,
+}
+
+impl<'a> Context<'a> {
+    fn source_info(&self, file: &FileDescriptorProto) -> &SourceInfo<'a> {
+        &self.source_infos[file.get_name()]
+    }
+
+    /// Where an error about an element, by its fully qualified name, originates.
+    fn locate(&self, file: &FileDescriptorProto, element: &str, path: &Path) -> Location {
+        self.source_info(file).locate(element, path)
+    }
+
+    /// The description made of the comments of an element, empty when it has none.
+    fn comments(&self, file: &FileDescriptorProto, path: &Path) -> String {
+        self.source_info(file).comments(path, &self.config.comments)
+    }
+
+    fn options(&self, proto_type_name: &str) -> Option<&GraphqlOptions> {
+        self.declarations
+            .get(proto_type_name)
+            .map(Declaration::options)
+    }
+
+    /// The GraphQL name of a message or an enum, by its fully qualified name with a leading dot.
+    fn type_name(&self, proto_type_name: &str) -> Result<String, ErrorKind> {
+        let declaration = self.declarations.get(proto_type_name);
+        self.type_names.name(
+            proto_type_name,
+            declaration.map_or("", |declaration| declaration.file().get_package()),
+            declaration.and_then(|declaration| declaration.options().name.as_deref()),
+        )
+    }
+
+    /// Whether the object type and the input type of a message are rendered, by its fully
+    /// qualified name with a leading dot. An enum is rendered when either is.
+    fn usage(&self, proto_type_name: &str) -> Usage {
+        match self.reachable {
+            Some(ref reachable) => Usage {
+                output: reachable.outputs.contains(proto_type_name),
+                input: reachable.inputs.contains(proto_type_name),
+            },
+            None => Usage::ALL,
+        }
+    }
+
+    fn is_used(&self, proto_type_name: &str) -> bool {
+        let usage = self.usage(proto_type_name);
+        usage.output || usage.input
+    }
+
+    /// Whether a message or an enum is skipped with the `graphql` options, by itself or along
+    /// with an enclosing message.
+    fn is_skipped(&self, proto_type_name: &str) -> bool {
+        let mut scope = proto_type_name;
+        loop {
+            if self.options(scope).is_some_and(|options| options.skip) {
+                return true;
+            }
+            match scope.rfind('.') {
+                Some(idx) if idx > 0 => scope = &scope[..idx],
+                _ => return false,
+            }
+        }
+    }
+}
 
 fn proto_field_type_to_gql_type(
     field_type: FieldDescriptorProto_Type,
     type_name: &str,
     label: FieldDescriptorProto_Label,
-) -> FieldType {
-    FieldType {
+    context: &Context,
+) -> Result<FieldType, ErrorKind> {
+    let well_known = WellKnownType::from_type_name(type_name);
+    // Wrappers are rendered as the scalar they wrap.
+    let field_type = match well_known {
+        Some(WellKnownType::Wrapper(wrapped)) => wrapped,
+        _ => field_type,
+    };
+
+    if context.is_skipped(type_name) {
+        return Err(ErrorKind::SkippedType(
+            support::strip_leading_dots(type_name).to_string(),
+        ));
+    }
+
+    let (type_name, gql_name) = match field_type {
+        // The well-known types have a representation of their own.
+        FieldDescriptorProto_Type::TYPE_MESSAGE if well_known.is_some() => (type_name, None),
+        FieldDescriptorProto_Type::TYPE_MESSAGE
+        | FieldDescriptorProto_Type::TYPE_GROUP
+        | FieldDescriptorProto_Type::TYPE_ENUM => (type_name, Some(context.type_name(type_name)?)),
+        scalar => (
+            context
+                .config
+                .scalars
+                .get(scalar)
+                .expect("every scalar type maps to a GraphQL scalar"),
+            None,
+        ),
+    };
+
+    let unspecified = match context.declarations.get(type_name) {
+        Some(&Declaration::Enum { enum_, .. }) => match context.config.unspecified {
+            Unspecified::Keep => None,
+            Unspecified::Null => unspecified_value(enum_).map(|value| value.get_name().to_string()),
+        },
+        _ => None,
+    };
+    let zero_value = match context.declarations.get(type_name) {
+        Some(&Declaration::Enum { enum_, .. }) => enum_
+            .get_value()
+            .iter()
+            .find(|value| value.get_number() == 0)
+            .map(|value| value.get_name().to_string()),
+        _ => None,
+    };
+
+    let type_ = FieldType {
         proto_type: field_type,
         type_name: type_name.to_string(),
         label,
+        well_known,
+        map_entry: None,
+        gql_name,
+        unspecified,
+        zero_value,
+    };
+    if type_.is_scalar() {
+        claim_scalar(&type_.named_type(), context)?;
     }
+    Ok(type_)
 }
 
-fn fields_to_gql(
-    fields: &[FieldDescriptorProto],
-    source_info: &SourceCodeInfo,
-    path_prefix: &[i32],
-) -> Vec<Field> {
-    fields
-        .iter()
-        .map(|f| {
-            let mut full_path = path_prefix.to_owned();
-            full_path.push(2); // it's a field
-            full_path.push(f.get_number() - 1); // that field
-
-            let comment: String = source_info
-                .get_location()
-                .iter()
-                .filter(|loc| {
-                    // https://developers.google.com/protocol-buffers/docs/reference/java/com/google/protobuf/DescriptorProtos.SourceCodeInfo.Location#getPath-int-
-                    loc.get_path() == full_path.as_slice()
-                })
-                .map(|loc| {
-                    format!(
-                        "{}{}",
-                        loc.get_leading_comments(),
-                        loc.get_trailing_comments()
-                    )
-                })
-                .collect();
+/// Keeps messages and enums from taking the name of a scalar the schema uses.
+fn claim_scalar(name: &str, context: &Context) -> Result<(), ErrorKind> {
+    context
+        .type_names
+        .claim(name, &format!("the {} scalar", name))
+}
+
+/// The type of a field, with the key and value types of map fields, which protoc declares as
+/// repeated fields of a nested `map_entry` message.
+fn field_to_gql_type(
+    field: &FieldDescriptorProto,
+    context: &Context,
+) -> Result<FieldType, ErrorKind> {
+    let mut type_ = proto_field_type_to_gql_type(
+        field.get_field_type(),
+        field.get_type_name(),
+        field.get_label(),
+        context,
+    )?;
+
+    // protoc only lets map fields reference map entries.
+    let entry = match context.declarations.get(field.get_type_name()) {
+        Some(&Declaration::Message { message, .. }) if message.get_options().get_map_entry() => {
+            Some(message)
+        }
+        _ => None,
+    };
+    if let Some(entry) = entry {
+        // protoc always declares the key and then the value.
+        let entry_type = |entry_field: &FieldDescriptorProto| {
+            proto_field_type_to_gql_type(
+                entry_field.get_field_type(),
+                entry_field.get_type_name(),
+                FieldDescriptorProto_Label::LABEL_OPTIONAL,
+                context,
+            )
+        };
+        type_.map_entry = Some(Box::new(MapEntry {
+            key: entry_type(&entry.get_field()[0])?,
+            value: entry_type(&entry.get_field()[1])?,
+            style: context.config.map_style,
+        }));
+        if type_.is_json_map() {
+            claim_scalar(&type_.named_type(), context)?;
+        }
+    }
+
+    Ok(type_)
+}
+
+/// Whether a method is exposed as a query, a mutation or a subscription. Server and
+/// bidirectional streaming methods are subscriptions, client streaming methods are mutations.
+/// Otherwise the `graphql` options win, then the configured option, then the idempotency level,
+/// then the configured name prefixes. Anything else may have side effects, so it is a mutation.
+fn method_operation(
+    method: &MethodDescriptorProto,
+    graphql_options: &GraphqlOptions,
+    config: &Config,
+) -> Operation {
+    if method.get_server_streaming() {
+        return Operation::Subscription;
+    } else if method.get_client_streaming() {
+        return Operation::Mutation;
+    }
+
+    if let Some(operation) = graphql_options.operation {
+        return operation;
+    }
+
+    let options = method.get_options().get_unknown_fields();
+
+    if let Some(number) = config.operation_option {
+        match support::unknown_varint(options, number) {
+            Some(1) => return Operation::Query,
+            Some(2) => return Operation::Mutation,
+            _ => (),
+        }
+    }
+
+    // `MethodOptions.idempotency_level`, which postdates our protobuf version.
+    match support::unknown_varint(options, 34) {
+        Some(1) => return Operation::Query,    // NO_SIDE_EFFECTS
+        Some(2) => return Operation::Mutation, // IDEMPOTENT
+        _ => (),
+    }
+
+    // The prefix has to be a whole word: `GetPizza`, but not `Getaway`.
+    let name = method.get_name();
+    let is_query = config.query_prefixes.iter().any(|prefix| {
+        name.starts_with(prefix.as_str())
+            && name[prefix.len()..]
+                .chars()
+                .next()
+                .is_none_or(|next| !next.is_lowercase())
+    });
+    if is_query {
+        Operation::Query
+    } else {
+        Operation::Mutation
+    }
+}
+
+fn method_to_gql(
+    method: &MethodDescriptorProto,
+    options: GraphqlOptions,
+    comment: &str,
+    context: &Context,
+) -> Result<Method, ErrorKind> {
+    let method_type = |type_name, streaming| {
+        proto_field_type_to_gql_type(
+            FieldDescriptorProto_Type::TYPE_MESSAGE,
+            type_name,
+            // A stream of requests is a list argument.
+            if streaming {
+                FieldDescriptorProto_Label::LABEL_REPEATED
+            } else {
+                FieldDescriptorProto_Label::LABEL_OPTIONAL
+            },
+            context,
+        )
+    };
+
+    let input_type = method_type(method.get_input_type(), method.get_client_streaming())?;
+    Ok(Method {
+        descriptor: method.clone(),
+        // The input message is the only argument.
+        argument_name: match input_type.well_known {
+            Some(WellKnownType::Empty) => None,
+            _ => Some(field_name(
+                &input_type.named_type().to_snake_case(),
+                None,
+                context.config,
+            )),
+        },
+        name: options
+            .name
+            .clone()
+            .unwrap_or_else(|| method.get_name().to_mixed_case()),
+        renamed: options.name.is_some(),
+        input_type,
+        output_type: method_type(method.get_output_type(), false)?,
+        operation: method_operation(method, &options, context.config),
+        deprecation_reason: options.deprecation(method.get_options().get_deprecated(), comment),
+        description: options.description.or(if comment.is_empty() {
+            None
+        } else {
+            Some(comment.to_string())
+        }),
+    })
+}
+
+/// `FieldDescriptorProto.proto3_optional`, which postdates our protobuf version.
+fn is_proto3_optional(field: &FieldDescriptorProto) -> bool {
+    support::unknown_varint(field.get_unknown_fields(), 17) == Some(1)
+}
+
+/// Whether the field is never null in object types, given its presence semantics: message
+/// fields and fields that track presence are null when unset, repeated fields are empty lists.
+/// Enums are null when their zero value is left out of the schema.
+fn is_required(
+    field: &FieldDescriptorProto,
+    type_: &FieldType,
+    file: &FileDescriptorProto,
+) -> bool {
+    if type_.is_repeated() {
+        true
+    } else if type_.is_message() || type_.unspecified.is_some() || is_proto3_optional(field) {
+        false
+    } else if file.get_syntax() == "proto3" {
+        true
+    } else {
+        field.get_label() == FieldDescriptorProto_Label::LABEL_REQUIRED
+    }
+}
+
+/// The GraphQL name of a field, or of a oneof or an argument, which have no `json_name`.
+fn field_name(name: &str, json_name: Option<&str>, config: &Config) -> String {
+    match config.field_case {
+        FieldCase::Json => match json_name {
+            Some(json_name) if !json_name.is_empty() => json_name.to_string(),
+            _ => to_json_name(name),
+        },
+        FieldCase::Preserve => name.to_string(),
+        FieldCase::Camel => name.to_mixed_case(),
+        FieldCase::Snake => name.to_snake_case(),
+    }
+}
+
+/// The `json_name` protoc gives a field without the `json_name` option: the underscores are
+/// dropped, and the letters following them capitalized.
+fn to_json_name(name: &str) -> String {
+    let mut json_name = String::with_capacity(name.len());
+    let mut capitalize_next = false;
+    for c in name.chars() {
+        if c == '_' {
+            capitalize_next = true;
+        } else if capitalize_next {
+            json_name.extend(c.to_uppercase());
+            capitalize_next = false;
+        } else {
+            json_name.push(c);
+        }
+    }
+    json_name
+}
+
+/// The fully qualified name of a proto element, without the leading dot.
+fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+/// Whether the type of a field can be replaced with a scalar of the `graphql` options: it has to
+/// be rendered as a scalar already, other than a well-known type with a representation of its own.
+fn is_scalar_overridable(type_: &FieldType) -> bool {
+    type_.is_scalar()
+        && type_.map_entry.is_none()
+        && type_
+            .well_known
+            .and_then(|well_known| well_known.gql_type())
+            .is_none()
+}
+
+/// The fields of a message, along with their descriptors, leaving out the skipped ones.
+fn fields_to_gql<'a>(
+    message: &'a DescriptorProto,
+    file: &FileDescriptorProto,
+    message_full_name: &str,
+    message_path: &Path,
+    context: &Context,
+) -> Result<Vec<(&'a FieldDescriptorProto, Field)>, Error> {
+    let mut fields = Vec::new();
+    for (idx, f) in message.get_field().iter().enumerate() {
+        let field_path = message_path.field(idx);
+        let located = |kind| {
+            Error::at(
+                kind,
+                context.locate(
+                    file,
+                    &qualified_name(message_full_name, f.get_name()),
+                    &field_path,
+                ),
+            )
+        };
+
+        let options =
+            GraphqlOptions::parse(f.get_options().get_unknown_fields()).map_err(&located)?;
+        if options.skip {
+            continue;
+        }
+
+        let mut type_ = field_to_gql_type(f, context).map_err(&located)?;
+        if let Some(ref scalar) = options.scalar {
+            if !is_scalar_overridable(&type_) {
+                return Err(located(ErrorKind::InvalidOption(format!(
+                    "the scalar {} can only replace a scalar or a wrapper type",
+                    scalar
+                ))));
+            }
+            type_.type_name = scalar.clone();
+        }
+
+        if let Some(what) = legacy_grpc_limitation(f, &type_, context.config) {
+            return Err(located(ErrorKind::LegacyGrpc(what)));
+        }
+
+        let comment = context.comments(file, &field_path);
+        let required = match options.nullability {
+            Some(nullability) => nullability == Nullability::NonNull,
+            None => is_required(f, &type_, file),
+        };
+        let deprecation_reason = options.deprecation(f.get_options().get_deprecated(), &comment);
+        fields.push((
+            f,
             Field {
-                description: if comment.is_empty() {
+                description: options.description.or(if comment.is_empty() {
                     None
                 } else {
                     Some(comment)
-                },
-                name: f.get_name().to_string(),
-                type_: proto_field_type_to_gql_type(
-                    f.get_field_type(),
-                    f.get_type_name(),
-                    f.get_label(),
-                ),
-                required: true,
-            }
-        })
-        .collect()
+                }),
+                name: options.name.unwrap_or_else(|| {
+                    field_name(f.get_name(), Some(f.get_json_name()), context.config)
+                }),
+                proto_name: f.get_name().to_string(),
+                required,
+                deprecation_reason,
+                type_,
+            },
+        ));
+    }
+
+    Ok(fields)
 }
 
-fn message_type_to_gql(
+/// What keeps the resolvers from converting the values of a field loaded by `grpc.load`, which
+/// reads enums as numbers and does not tell which member of a oneof is set.
+fn legacy_grpc_limitation(
+    field: &FieldDescriptorProto,
+    type_: &FieldType,
+    config: &Config,
+) -> Option<&'static str> {
+    if config.grpc != Grpc::Legacy || !config.outputs.resolvers {
+        return None;
+    }
+
+    let value_type = match type_.map_entry {
+        Some(ref entry) => &entry.value,
+        None => type_,
+    };
+    if value_type.proto_type == FieldDescriptorProto_Type::TYPE_ENUM {
+        Some("reads enum values as numbers")
+    } else if field.has_oneof_index() && !is_proto3_optional(field) {
+        Some("does not tell which member of a oneof is set")
+    } else {
+        None
+    }
+}
+
+/// The object type of a message, without its nested messages and enums.
+fn message_to_object(
     message: &DescriptorProto,
-    source_info: &SourceCodeInfo,
-    path_prefix: &[i32],
-    package_name: &str,
-    gql_type_defs: &mut GqlTypeDefs,
-) {
-    let description: String = source_info
-        .get_location()
+    file: &FileDescriptorProto,
+    full_name: &str,
+    path: &Path,
+    context: &Context,
+) -> Result<ObjectType, Error> {
+    let config = context.config;
+    let located = |kind| Error::at(kind, context.locate(file, full_name, path));
+    let options =
+        GraphqlOptions::parse(message.get_options().get_unknown_fields()).map_err(&located)?;
+    let name = context
+        .type_name(&format!(".{}", full_name))
+        .map_err(&located)?;
+    let description = context.comments(file, path);
+
+    let mut oneofs: Vec<Oneof> = message
+        .get_oneof_decl()
         .iter()
-        .filter(|loc| loc.get_path() == path_prefix)
-        .map(|loc| loc.get_leading_comments())
+        .enumerate()
+        .map(|(idx, oneof)| {
+            let description = context.comments(file, &path.oneof(idx));
+            Oneof {
+                description: if description.is_empty() {
+                    None
+                } else {
+                    Some(description)
+                },
+                name: field_name(oneof.get_name(), None, config),
+                proto_name: oneof.get_name().to_string(),
+                type_name: format!("{}{}", name, oneof.get_name().to_camel_case()),
+                style: config.oneof_style,
+                fields: Vec::new(),
+            }
+        })
         .collect();
-    let fields = fields_to_gql(message.get_field(), source_info, path_prefix);
-    let name = if package_name.is_empty() {
-        message.get_name().to_string()
-    } else {
-        format!(
-            "{}{}",
-            package_name.replace(".", "_").to_camel_case(),
-            message.get_name()
-        ).to_string()
-    };
-    let object = ObjectType {
+    let all_fields = fields_to_gql(message, file, full_name, path, context)?;
+    let mut fields = Vec::new();
+    for (descriptor, field) in all_fields {
+        // proto3 optional fields are alone in synthetic oneofs, which only track presence.
+        match oneofs.get_mut(descriptor.get_oneof_index() as usize) {
+            Some(oneof) if descriptor.has_oneof_index() && !is_proto3_optional(descriptor) => {
+                oneof.fields.push(field)
+            }
+            _ => fields.push(field),
+        }
+    }
+    oneofs.retain(|oneof| !oneof.fields.is_empty());
+
+    Ok(ObjectType {
         name,
         fields,
-        description: if description.is_empty() {
+        oneofs,
+        description: options.description.or(if description.is_empty() {
             None
         } else {
             Some(description)
-        },
-    };
+        }),
+    })
+}
 
-    for e in message.get_enum_type().iter().enumerate().map(|(idx, e)| {
-        let mut full_path = path_prefix.to_owned();
-        full_path.push(4); // this is an enum
-        full_path.push(idx as i32);
-        EnumType::from_proto(e, source_info, &full_path, Some(message.get_name()))
-    }) {
-        gql_type_defs.push_enum(e);
+/// Gives their names to the types generated for the object type of a message, so that they
+/// cannot be the names of other types: its input type and the ones of its map entries, and the
+/// types of its oneofs.
+fn claim_generated_names(
+    object: &ObjectType,
+    full_name: &str,
+    usage: Usage,
+    context: &Context,
+) -> Result<(), ErrorKind> {
+    let mut names = Vec::new();
+    if usage.input {
+        names.push((
+            format!("{}Input", object.name),
+            format!("the input type of {}", full_name),
+        ));
+        for field in object.all_fields() {
+            if field.type_.map_entry.is_some() && !field.type_.is_json_map() {
+                names.push((
+                    format!("{}Input", field.type_.named_type()),
+                    format!("the input type of {}", &field.type_.type_name[1..]),
+                ));
+            }
+        }
+    }
+    for oneof in object.oneofs.iter() {
+        let owner = format!("the oneof {}.{}", full_name, oneof.proto_name);
+        if usage.output {
+            for name in oneof.output_type_names() {
+                names.push((name, owner.clone()));
+            }
+        }
+        if usage.input {
+            names.push((format!("{}Input", oneof.type_name), owner));
+        }
     }
 
-    gql_type_defs.push_object(object);
+    for (name, owner) in names {
+        context.type_names.claim(&name, &owner)?;
+    }
+    Ok(())
+}
+
+fn message_type_to_gql(
+    message: &DescriptorProto,
+    file: &FileDescriptorProto,
+    full_name: &str,
+    path: &Path,
+    context: &Context,
+    gql_type_defs: &mut GqlTypeDefs,
+) -> Result<(), Error> {
+    // Along with its nested messages and enums.
+    if context.is_skipped(&format!(".{}", full_name)) {
+        return Ok(());
+    }
+
+    for (idx, e) in message.get_enum_type().iter().enumerate() {
+        let enum_name = qualified_name(full_name, e.get_name());
+        let proto_name = format!(".{}", enum_name);
+        if context.is_skipped(&proto_name) || !context.is_used(&proto_name) {
+            continue;
+        }
+
+        let enum_path = path.nested_enum(idx);
+        let enum_type = context
+            .type_name(&proto_name)
+            .and_then(|name| {
+                EnumType::from_proto(
+                    e,
+                    name,
+                    context.source_info(file),
+                    &enum_path,
+                    context.config,
+                )
+            })
+            .map_err(|kind| Error::at(kind, context.locate(file, &enum_name, &enum_path)))?;
+        gql_type_defs.push_enum(enum_type);
+    }
+
+    let usage = context.usage(&format!(".{}", full_name));
+    if usage.output || usage.input {
+        let object = message_to_object(message, file, full_name, path, context)?;
+        claim_generated_names(&object, full_name, usage, context)
+            .map_err(|kind| Error::at(kind, context.locate(file, full_name, path)))?;
+        gql_type_defs.push_object(object, usage);
+    }
 
     for (idx, nested_message) in message.get_nested_type().iter().enumerate() {
-        let mut nested_path_prefix = path_prefix.to_owned();
-        nested_path_prefix.push(3); // nested messages are the third field on message, see https://github.com/google/protobuf/blob/master/src/google/protobuf/descriptor.proto
-        nested_path_prefix.push(idx as i32);
-        let mut nested_message = nested_message.clone();
-        let name = nested_message.get_name().to_string();
-        nested_message.set_name(format!("{}{}", message.get_name(), name));
+        // Map entries are rendered along with their map fields.
+        if nested_message.get_options().get_map_entry() {
+            continue;
+        }
+
         message_type_to_gql(
-            &nested_message,
-            source_info,
-            &nested_path_prefix,
-            package_name,
+            nested_message,
+            file,
+            &qualified_name(full_name, nested_message.get_name()),
+            &path.nested_message(idx),
+            context,
             gql_type_defs,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// A message or an enum, as found by its fully qualified name with a leading dot, the way fields
+/// and methods reference them.
+enum Declaration<'a> {
+    Message {
+        file: &'a FileDescriptorProto,
+        message: &'a DescriptorProto,
+        path: Path,
+        options: GraphqlOptions,
+    },
+    Enum {
+        file: &'a FileDescriptorProto,
+        enum_: &'a EnumDescriptorProto,
+        path: Path,
+        options: GraphqlOptions,
+    },
+}
+
+impl<'a> Declaration<'a> {
+    fn file(&self) -> &'a FileDescriptorProto {
+        match *self {
+            Declaration::Message { file, .. } | Declaration::Enum { file, .. } => file,
+        }
+    }
+
+    fn options(&self) -> &GraphqlOptions {
+        match *self {
+            Declaration::Message { ref options, .. } | Declaration::Enum { ref options, .. } => {
+                options
+            }
+        }
+    }
+}
+
+/// The `graphql` options of a declaration, with errors located at it.
+fn declaration_options(
+    source_info: &SourceInfo,
+    full_name: &str,
+    path: &Path,
+    unknown_fields: &::protobuf::UnknownFields,
+) -> Result<GraphqlOptions, Error> {
+    GraphqlOptions::parse(unknown_fields).map_err(|kind| {
+        Error::at(
+            kind,
+            source_info.locate(support::strip_leading_dots(full_name), path),
+        )
+    })
+}
+
+fn index_messages<'a>(
+    file: &'a FileDescriptorProto,
+    source_info: &SourceInfo,
+    messages: &'a [DescriptorProto],
+    scope: &str,
+    parent_path: Option<&Path>,
+    declarations: &mut HashMap<String, Declaration<'a>>,
+) -> Result<(), Error> {
+    for (idx, message) in messages.iter().enumerate() {
+        let full_name = format!("{}.{}", scope, message.get_name());
+        let path = match parent_path {
+            Some(parent_path) => parent_path.nested_message(idx),
+            None => Path::message(idx),
+        };
+
+        for (enum_idx, enum_) in message.get_enum_type().iter().enumerate() {
+            let enum_name = format!("{}.{}", full_name, enum_.get_name());
+            let enum_path = path.nested_enum(enum_idx);
+            let options = declaration_options(
+                source_info,
+                &enum_name,
+                &enum_path,
+                enum_.get_options().get_unknown_fields(),
+            )?;
+            declarations.insert(
+                enum_name,
+                Declaration::Enum {
+                    file,
+                    enum_,
+                    path: enum_path,
+                    options,
+                },
+            );
+        }
+
+        index_messages(
+            file,
+            source_info,
+            message.get_nested_type(),
+            &full_name,
+            Some(&path),
+            declarations,
+        )?;
+
+        let options = declaration_options(
+            source_info,
+            &full_name,
+            &path,
+            message.get_options().get_unknown_fields(),
+        )?;
+        declarations.insert(
+            full_name,
+            Declaration::Message {
+                file,
+                message,
+                path,
+                options,
+            },
         );
     }
+
+    Ok(())
 }
 
-pub fn gen(
-    file_descriptors: &[FileDescriptorProto],
-    files_to_generate: &[String],
-) -> Vec<compiler_plugin::GenResult> {
-    let _files_map: HashMap<&str, &FileDescriptorProto> =
-        file_descriptors.iter().map(|f| (f.get_name(), f)).collect();
+fn index_declarations<'a>(
+    file_descriptors: &'a [FileDescriptorProto],
+    source_infos: &HashMap<&'a str, SourceInfo<'a>>,
+) -> Result<HashMap<String, Declaration<'a>>, Error> {
+    let mut declarations = HashMap::new();
 
-    let mut type_defs = GqlTypeDefs::new();
+    for file in file_descriptors {
+        let source_info = &source_infos[file.get_name()];
+        let scope = if file.get_package().is_empty() {
+            String::new()
+        } else {
+            format!(".{}", file.get_package())
+        };
 
-    // See https://developers.google.com/protocol-buffers/docs/reference/java/com/google/protobuf/DescriptorProtos.SourceCodeInfo.Location
-    // on where to get comment strings
+        index_messages(
+            file,
+            source_info,
+            file.get_message_type(),
+            &scope,
+            None,
+            &mut declarations,
+        )?;
+        for (idx, enum_) in file.get_enum_type().iter().enumerate() {
+            let enum_name = format!("{}.{}", scope, enum_.get_name());
+            let path = Path::enum_(idx);
+            let options = declaration_options(
+                source_info,
+                &enum_name,
+                &path,
+                enum_.get_options().get_unknown_fields(),
+            )?;
+            declarations.insert(
+                enum_name,
+                Declaration::Enum {
+                    file,
+                    enum_,
+                    path,
+                    options,
+                },
+            );
+        }
+    }
 
-    // println!("{:?}", files_map);
+    Ok(declarations)
+}
 
-    // let root_scope = RootScope {
-    //     file_descriptors: file_descriptors,
-    // };
+/// The messages and enums used by the methods of some services, by fully qualified name with a
+/// leading dot.
+#[derive(Default)]
+struct Reachable {
+    /// Reachable from the outputs of the methods, through the fields of the messages.
+    outputs: HashSet<String>,
+    /// Reachable from the inputs of the methods.
+    inputs: HashSet<String>,
+}
 
-    let mut results = Vec::new();
+fn is_skipped_option(unknown_fields: &::protobuf::UnknownFields) -> bool {
+    // Invalid options are reported when the element is rendered.
+    GraphqlOptions::parse(unknown_fields).is_ok_and(|options| options.skip)
+}
 
-    for file_name in files_to_generate {
-        let mut content: Vec<u8> = Vec::new();
-
-        for descriptor in file_descriptors.iter() {
-            for proto_service in descriptor.get_service() {
-                let service = Service {
-                    name: proto_service.get_name().to_string(),
-                    methods: proto_service.get_method().into(),
-                    origin_file_name: descriptor.get_name().to_string(),
-                };
-                type_defs.push_service(service);
-            }
+/// Adds `type_name` and the types reachable from it through the fields that are not skipped.
+fn reach(
+    type_name: &str,
+    declarations: &HashMap<String, Declaration>,
+    reached: &mut HashSet<String>,
+) {
+    let mut pending = vec![type_name.to_string()];
+    while let Some(type_name) = pending.pop() {
+        if !reached.insert(type_name.clone()) {
+            continue;
+        }
+
+        if let Some(&Declaration::Message { message, .. }) = declarations.get(&type_name) {
+            pending.extend(
+                message
+                    .get_field()
+                    .iter()
+                    .filter(|field| {
+                        !field.get_type_name().is_empty()
+                            && !is_skipped_option(field.get_options().get_unknown_fields())
+                    })
+                    .map(|field| field.get_type_name().to_string()),
+            );
+        }
+    }
+}
 
-            for (idx, message_type) in descriptor.get_message_type().iter().enumerate() {
-                message_type_to_gql(
-                    message_type,
-                    descriptor.get_source_code_info(),
-                    vec![4, idx as i32].as_slice(),
-                    descriptor.get_package(),
-                    &mut type_defs,
-                );
+/// The types used by the services of the given files that are not skipped.
+fn reachable_types(
+    files: &[&FileDescriptorProto],
+    declarations: &HashMap<String, Declaration>,
+) -> Reachable {
+    let mut reachable = Reachable::default();
+    let methods = files
+        .iter()
+        .flat_map(|file| file.get_service().iter())
+        .filter(|service| !is_skipped_option(service.get_options().get_unknown_fields()))
+        .flat_map(|service| service.get_method().iter())
+        .filter(|method| !is_skipped_option(method.get_options().get_unknown_fields()));
+    for method in methods {
+        reach(method.get_input_type(), declarations, &mut reachable.inputs);
+        reach(
+            method.get_output_type(),
+            declarations,
+            &mut reachable.outputs,
+        );
+    }
+
+    reachable
+}
+
+/// Adds the services, messages and enums declared in a file.
+fn push_file(
+    descriptor: &FileDescriptorProto,
+    context: &Context,
+    type_defs: &mut GqlTypeDefs,
+) -> Result<(), Error> {
+    for (service_idx, proto_service) in descriptor.get_service().iter().enumerate() {
+        let service_name = qualified_name(descriptor.get_package(), proto_service.get_name());
+        let service_path = Path::service(service_idx);
+        let options = GraphqlOptions::parse(proto_service.get_options().get_unknown_fields())
+            .map_err(|kind| {
+                Error::at(
+                    kind,
+                    context.locate(descriptor, &service_name, &service_path),
+                )
+            })?;
+        if options.skip {
+            continue;
+        }
+
+        let mut service = Service {
+            name: options
+                .name
+                .unwrap_or_else(|| proto_service.get_name().to_string()),
+            proto_name: proto_service.get_name().to_string(),
+            description: options.description.or_else(|| {
+                let comment = context.comments(descriptor, &service_path);
+                if comment.is_empty() {
+                    None
+                } else {
+                    Some(comment)
+                }
+            }),
+            methods: Vec::new(),
+            origin_file_name: descriptor.get_name().to_string(),
+            package: descriptor.get_package().to_string(),
+        };
+        for (idx, method) in proto_service.get_method().iter().enumerate() {
+            let method_path = service_path.method(idx);
+            let method_name = qualified_name(&service_name, method.get_name());
+            let located =
+                |kind| Error::at(kind, context.locate(descriptor, &method_name, &method_path));
+            let method_options = GraphqlOptions::parse(method.get_options().get_unknown_fields())
+                .map_err(&located)?;
+            if method_options.skip {
+                continue;
             }
 
-            for (idx, e) in descriptor.get_enum_type().iter().enumerate() {
-                type_defs.push_enum(EnumType::from_proto(
+            let comment = context.comments(descriptor, &method_path);
+            let method =
+                method_to_gql(method, method_options, &comment, context).map_err(&located)?;
+            type_defs
+                .claim_field(type_defs.method_field(&service, &method), &method_name)
+                .map_err(&located)?;
+            service.methods.push(method);
+        }
+        let located_service = |kind| {
+            Error::at(
+                kind,
+                context.locate(descriptor, &service_name, &service_path),
+            )
+        };
+        for field in type_defs.service_fields(&service) {
+            type_defs
+                .claim_field(field, &service_name)
+                .map_err(&located_service)?;
+        }
+        for type_name in type_defs.service_type_names(&service) {
+            let owner = format!("the service {}", service_name);
+            context
+                .type_names
+                .claim(&type_name, &owner)
+                .map_err(&located_service)?;
+        }
+        type_defs.push_service(service);
+    }
+
+    for (idx, message_type) in descriptor.get_message_type().iter().enumerate() {
+        let full_name = qualified_name(descriptor.get_package(), message_type.get_name());
+        // They have a GraphQL representation of their own.
+        if WellKnownType::from_type_name(&format!(".{}", full_name)).is_some() {
+            continue;
+        }
+
+        message_type_to_gql(
+            message_type,
+            descriptor,
+            &full_name,
+            &Path::message(idx),
+            context,
+            type_defs,
+        )?;
+    }
+
+    for (idx, e) in descriptor.get_enum_type().iter().enumerate() {
+        let full_name = qualified_name(descriptor.get_package(), e.get_name());
+        let proto_name = format!(".{}", full_name);
+        if context.is_skipped(&proto_name) || !context.is_used(&proto_name) {
+            continue;
+        }
+
+        let path = Path::enum_(idx);
+        let enum_type = context
+            .type_name(&format!(".{}", full_name))
+            .and_then(|name| {
+                EnumType::from_proto(
                     e,
-                    descriptor.get_source_code_info(),
-                    vec![5, idx as i32].as_slice(),
-                    None,
-                ));
+                    name,
+                    context.source_info(descriptor),
+                    &path,
+                    context.config,
+                )
+            })
+            .map_err(|kind| Error::at(kind, context.locate(descriptor, &full_name, &path)))?;
+        type_defs.push_enum(enum_type);
+    }
+
+    Ok(())
+}
+
+/// The type definitions of the given files. The messages and enums they reference from other
+/// files are added to the schema when `merged`, and otherwise only to the codecs of the resolvers.
+fn files_to_gql<'a>(
+    files: &[&FileDescriptorProto],
+    merged: bool,
+    context: &Context<'a>,
+) -> Result<GqlTypeDefs<'a>, Error> {
+    let mut type_defs = GqlTypeDefs::new(context.config);
+    for file in files {
+        push_file(file, context, &mut type_defs)?;
+    }
+
+    let emitted: HashSet<&str> = files.iter().map(|file| file.get_name()).collect();
+    let is_emitted = |file: &FileDescriptorProto| emitted.contains(file.get_name());
+    let mut pending: VecDeque<String> = type_defs.referenced_types().into_iter().collect();
+    let mut seen = HashSet::new();
+    while let Some(type_name) = pending.pop_front() {
+        if !seen.insert(type_name.clone()) {
+            continue;
+        }
+
+        match context.declarations.get(&type_name) {
+            Some(&Declaration::Message {
+                file,
+                message,
+                ref path,
+                ..
+            }) if !is_emitted(file) => {
+                let object = message_to_object(
+                    message,
+                    file,
+                    support::strip_leading_dots(&type_name),
+                    path,
+                    context,
+                )?;
+                pending.extend(object.referenced_types().into_iter().map(str::to_string));
+                if merged {
+                    let full_name = support::strip_leading_dots(&type_name);
+                    claim_generated_names(&object, full_name, context.usage(&type_name), context)
+                        .map_err(|kind| Error::at(kind, context.locate(file, full_name, path)))?;
+                    type_defs.push_object(object, context.usage(&type_name));
+                } else {
+                    type_defs.push_dependency_object(object);
+                }
             }
+            Some(&Declaration::Enum {
+                file,
+                enum_,
+                ref path,
+                ..
+            }) if merged && !is_emitted(file) => {
+                let enum_type = context
+                    .type_name(&type_name)
+                    .and_then(|name| {
+                        EnumType::from_proto(
+                            enum_,
+                            name,
+                            context.source_info(file),
+                            path,
+                            context.config,
+                        )
+                    })
+                    .map_err(|kind| {
+                        Error::at(
+                            kind,
+                            context.locate(file, support::strip_leading_dots(&type_name), path),
+                        )
+                    })?;
+                type_defs.push_enum(enum_type);
+            }
+            _ => (),
         }
+    }
 
-        content.extend(format!("{}", type_defs).into_bytes());
+    Ok(type_defs)
+}
 
+/// Renders the outputs enabled in the config, named after `base_name`.
+fn render_outputs(
+    base_name: &str,
+    type_defs: &GqlTypeDefs,
+    config: &Config,
+    results: &mut Vec<GenResult>,
+) -> Result<(), Error> {
+    if config.outputs.schema {
+        let mut content = String::new();
+        write!(content, "{}", type_defs)?;
+        // Without root types, the last type is followed by the blank line before them.
+        let content = content.trim_end();
         results.push(GenResult {
-            name: format!("{}.out", file_name),
-            content,
+            name: format!("{}.out", base_name),
+            content: content.as_bytes().to_vec(),
         });
+    }
 
+    if config.outputs.type_defs {
         results.push(GenResult {
-            name: format!("{}-type-defs.js", file_name),
-            content: type_defs.render_js_module().unwrap().into_bytes(),
+            name: format!("{}-type-defs.js", base_name),
+            content: type_defs.render_js_module()?.into_bytes(),
         });
+    }
 
+    if config.outputs.resolvers {
         results.push(GenResult {
-            name: format!("{}-resolvers.js", file_name),
-            content: type_defs.render_resolvers().unwrap().into_bytes(),
+            name: format!("{}-resolvers.js", base_name),
+            content: type_defs.render_resolvers()?.into_bytes(),
         });
     }
 
-    results
+    Ok(())
+}
+
+pub fn gen(
+    file_descriptors: &[FileDescriptorProto],
+    files_to_generate: &[String],
+    config: &Config,
+) -> Result<Vec<GenResult>, Error> {
+    let source_infos: HashMap<&str, SourceInfo> = file_descriptors
+        .iter()
+        .map(|file| (file.get_name(), SourceInfo::new(file)))
+        .collect();
+    // protoc passes all the files to generate along with their dependencies.
+    let files_by_name: HashMap<&str, &FileDescriptorProto> = file_descriptors
+        .iter()
+        .map(|file| (file.get_name(), file))
+        .collect();
+    let files: Vec<&FileDescriptorProto> = files_to_generate
+        .iter()
+        .filter_map(|name| files_by_name.get(name.as_str()).cloned())
+        .collect();
+    let declarations = index_declarations(file_descriptors, &source_infos)?;
+    let reachable = match config.types {
+        Types::Reachable => Some(reachable_types(&files, &declarations)),
+        Types::All => None,
+    };
+    let context = Context {
+        config,
+        declarations,
+        source_infos,
+        type_names: TypeNames::new(
+            config.type_naming,
+            files.iter().map(|file| file.get_package()),
+        ),
+        reachable,
+    };
+
+    let mut results = Vec::new();
+    match config.emit {
+        Emit::PerFile => {
+            for file in files {
+                let type_defs = files_to_gql(&[file], false, &context)?;
+                render_outputs(file.get_name(), &type_defs, config, &mut results)?;
+            }
+        }
+        Emit::Merged => {
+            let type_defs = files_to_gql(&files, true, &context)?;
+            render_outputs("schema", &type_defs, config, &mut results)?;
+        }
+    }
+
+    Ok(results)
+}
+
+fn gen_response(request: &CodeGeneratorRequest) -> CodeGeneratorResponse {
+    let mut response = CodeGeneratorResponse::new();
+    // `CodeGeneratorResponse.supported_features`, which postdates our protobuf version, set to
+    // FEATURE_PROTO3_OPTIONAL, without which protoc refuses to run plugins on files using proto3
+    // optional fields.
+    response.mut_unknown_fields().add_varint(2, 1);
+
+    let results = Config::from_parameter(request.get_parameter())
+        .map_err(Error::from)
+        .and_then(|config| {
+            gen(
+                request.get_proto_file(),
+                request.get_file_to_generate(),
+                &config,
+            )
+        });
+
+    let results = match results {
+        Ok(results) => results,
+        Err(err) => {
+            response.set_error(err.to_string());
+            return response;
+        }
+    };
+
+    response.set_file(
+        results
+            .into_iter()
+            .map(|result| {
+                let mut file = CodeGeneratorResponse_File::new();
+                file.set_name(result.name);
+                file.set_content(String::from_utf8(result.content).unwrap());
+                file
+            })
+            .collect(),
+    );
+
+    response
 }
 
 pub fn protoc_gen_apollo_main() {
-    compiler_plugin::plugin_main(gen);
+    let request: CodeGeneratorRequest =
+        protobuf::parse_from_reader(&mut ::std::io::stdin()).unwrap();
+    gen_response(&request)
+        .write_to_writer(&mut ::std::io::stdout())
+        .unwrap();
 }
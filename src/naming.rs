@@ -0,0 +1,192 @@
+//! The GraphQL names of the messages and enums, used both where they are defined and where they
+//! are referenced.
+
+use config::TypeNaming;
+use error::ErrorKind;
+use heck::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+pub struct TypeNames {
+    naming: TypeNaming,
+    /// The package segments stripped with `TypeNaming::StripCommonPrefix`.
+    common_prefix: Vec<String>,
+    /// What each GraphQL name was given to: a proto type by fully qualified name, or the element
+    /// a generated type is made for.
+    given: RefCell<HashMap<String, String>>,
+}
+
+impl TypeNames {
+    /// The names under the given strategy, with the common prefix of the packages of the
+    /// generated files.
+    pub fn new<'a, I>(naming: TypeNaming, packages: I) -> TypeNames
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut common_prefix: Option<Vec<String>> = None;
+        for package in packages {
+            let segments = package
+                .split('.')
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string);
+            common_prefix = Some(match common_prefix {
+                None => segments.collect(),
+                Some(prefix) => prefix
+                    .into_iter()
+                    .zip(segments)
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect(),
+            });
+        }
+
+        // The root types are there whether or not the services need them.
+        let given = ["Query", "Mutation", "Subscription"]
+            .iter()
+            .map(|root| (root.to_string(), format!("the {} root type", root)))
+            .collect();
+        TypeNames {
+            naming,
+            common_prefix: common_prefix.unwrap_or_default(),
+            given: RefCell::new(given),
+        }
+    }
+
+    /// The name of a message or an enum, by its fully qualified name with a leading dot and the
+    /// package of its file, unless it is renamed with the `graphql` options. Fails when another
+    /// type already has the name.
+    pub fn name(
+        &self,
+        proto_type_name: &str,
+        package: &str,
+        renamed: Option<&str>,
+    ) -> Result<String, ErrorKind> {
+        let name = match renamed {
+            Some(renamed) => renamed.to_string(),
+            None => self.derived_name(proto_type_name, package),
+        };
+        self.claim(&name, &proto_type_name[1..])?;
+        Ok(name)
+    }
+
+    /// Gives a name to a type the generator makes up, such as an input type or the union of a
+    /// oneof, or to a scalar, described by `owner`. Fails when another type already has the name.
+    pub fn claim(&self, name: &str, owner: &str) -> Result<(), ErrorKind> {
+        let mut given = self.given.borrow_mut();
+        match given.get(name) {
+            Some(other) if other != owner => Err(ErrorKind::NameCollision {
+                name: name.to_string(),
+                first: other.clone(),
+                second: owner.to_string(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                given.insert(name.to_string(), owner.to_string());
+                Ok(())
+            }
+        }
+    }
+
+    fn derived_name(&self, proto_type_name: &str, package: &str) -> String {
+        let full_name = proto_type_name.trim_start_matches('.');
+        let package_segments: Vec<&str> = package.split('.').filter(|s| !s.is_empty()).collect();
+        // The names of the enclosing messages and of the type.
+        let scoped_name = if package_segments.is_empty() {
+            full_name
+        } else {
+            &full_name[package.len() + 1..]
+        };
+
+        let prefix_segments: &[&str] = match self.naming {
+            TypeNaming::Package => &package_segments,
+            TypeNaming::StripCommonPrefix
+                if self.common_prefix.len() <= package_segments.len()
+                    && package_segments
+                        .iter()
+                        .zip(self.common_prefix.iter())
+                        .all(|(a, b)| a == b) =>
+            {
+                &package_segments[self.common_prefix.len()..]
+            }
+            TypeNaming::StripCommonPrefix => &package_segments,
+            TypeNaming::Short => &[],
+        };
+
+        let mut name: String = prefix_segments
+            .iter()
+            .map(|segment| segment.to_camel_case())
+            .collect();
+        name.extend(scoped_name.split('.'));
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_follow_the_strategy() {
+        let packages = vec!["acme.pizza.v1", "acme.pizza.v2"];
+        let names = |naming| TypeNames::new(naming, packages.clone());
+
+        let package = names(TypeNaming::Package);
+        assert_eq!(
+            package.name(".acme.pizza.v1.Pizza.Size", "acme.pizza.v1", None),
+            Ok("AcmePizzaV1PizzaSize".to_string())
+        );
+        assert_eq!(package.name(".Pizza", "", None), Ok("Pizza".to_string()));
+
+        let stripped = names(TypeNaming::StripCommonPrefix);
+        assert_eq!(
+            stripped.name(".acme.pizza.v1.Pizza.Size", "acme.pizza.v1", None),
+            Ok("V1PizzaSize".to_string())
+        );
+        assert_eq!(
+            stripped.name(".acme.oven.Oven", "acme.oven", None),
+            Ok("AcmeOvenOven".to_string())
+        );
+
+        let short = names(TypeNaming::Short);
+        assert_eq!(
+            short.name(".acme.pizza.v1.Pizza.Size", "acme.pizza.v1", None),
+            Ok("PizzaSize".to_string())
+        );
+        assert_eq!(
+            short.name(".acme.pizza.v1.Pie", "acme.pizza.v1", Some("Tart")),
+            Ok("Tart".to_string())
+        );
+    }
+
+    #[test]
+    fn collisions_are_errors() {
+        let names = TypeNames::new(TypeNaming::Short, vec!["acme.pizza.v1", "acme.pizza.v2"]);
+        assert!(names
+            .name(".acme.pizza.v1.Pizza", "acme.pizza.v1", None)
+            .is_ok());
+        // Asking again for the same type is fine.
+        assert!(names
+            .name(".acme.pizza.v1.Pizza", "acme.pizza.v1", None)
+            .is_ok());
+        assert_eq!(
+            names.name(".acme.pizza.v2.Pizza", "acme.pizza.v2", None),
+            Err(ErrorKind::NameCollision {
+                name: "Pizza".to_string(),
+                first: "acme.pizza.v1.Pizza".to_string(),
+                second: "acme.pizza.v2.Pizza".to_string(),
+            })
+        );
+
+        assert!(names
+            .claim("PizzaInput", "the input type of acme.pizza.v1.Pizza")
+            .is_ok());
+        assert_eq!(
+            names.name(".acme.pizza.v1.Pizza.Input", "acme.pizza.v1", None),
+            Err(ErrorKind::NameCollision {
+                name: "PizzaInput".to_string(),
+                first: "the input type of acme.pizza.v1.Pizza".to_string(),
+                second: "acme.pizza.v1.Pizza.Input".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! The options declared in `proto/graphql/options.proto`.
+//!
+//! We do not link code generated from that file, so protoc's descriptors hold the extensions as
+//! unknown fields of the descriptor options, which we decode here.
+
+use error::ErrorKind;
+use gql::Operation;
+use protobuf::{CodedInputStream, ProtobufResult, UnknownFields, UnknownValue};
+
+/// The number of the `graphql` extension of every kind of descriptor options.
+pub const EXTENSION_NUMBER: u32 = 52480;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nullability {
+    Nullable,
+    NonNull,
+}
+
+/// The options set on a field, a message, an enum, an enum value, a service or a method. The
+/// option messages share their field numbers, and each kind only declares the ones that apply to
+/// it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphqlOptions {
+    pub name: Option<String>,
+    pub skip: bool,
+    pub description: Option<String>,
+    pub deprecation_reason: Option<String>,
+    pub nullability: Option<Nullability>,
+    pub scalar: Option<String>,
+    pub operation: Option<Operation>,
+}
+
+impl GraphqlOptions {
+    /// Reads the extension from the unknown fields of a descriptor options message. Like for any
+    /// message field, the occurrences are merged.
+    pub fn parse(unknown_fields: &UnknownFields) -> Result<GraphqlOptions, ErrorKind> {
+        let mut options = GraphqlOptions::default();
+        let occurrences = unknown_fields
+            .get(EXTENSION_NUMBER)
+            .map(|values| values.length_delimited.as_slice())
+            .unwrap_or(&[]);
+        for bytes in occurrences {
+            options.merge(bytes).map_err(|err| {
+                ErrorKind::InvalidOption(format!("malformed graphql options: {}", err))
+            })?;
+        }
+
+        Ok(options)
+    }
+
+    /// The reason an element is deprecated for, if it is: the reason of these options, or else
+    /// the comment of an element marked `deprecated` in its descriptor options, joined on a
+    /// single line. The reason is empty when the element is deprecated without comment.
+    pub fn deprecation(&self, deprecated: bool, comment: &str) -> Option<String> {
+        if self.deprecation_reason.is_some() {
+            return self.deprecation_reason.clone();
+        }
+
+        if deprecated {
+            let lines: Vec<&str> = comment
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect();
+            Some(lines.join(" "))
+        } else {
+            None
+        }
+    }
+
+    fn merge(&mut self, bytes: &[u8]) -> ProtobufResult<()> {
+        // proto3 does not tell an empty string from an unset one.
+        fn string(bytes: Vec<u8>) -> ProtobufResult<Option<String>> {
+            let string = String::from_utf8(bytes).map_err(|err| err.utf8_error())?;
+            Ok(if string.is_empty() {
+                None
+            } else {
+                Some(string)
+            })
+        }
+
+        let mut input = CodedInputStream::from_bytes(bytes);
+        while !input.eof()? {
+            let (number, wire_type) = input.read_tag_unpack()?;
+            match (number, input.read_unknown(wire_type)?) {
+                (1, UnknownValue::LengthDelimited(name)) => self.name = string(name)?,
+                (2, UnknownValue::Varint(skip)) => self.skip = skip != 0,
+                (3, UnknownValue::LengthDelimited(description)) => {
+                    self.description = string(description)?
+                }
+                (4, UnknownValue::LengthDelimited(reason)) => {
+                    self.deprecation_reason = string(reason)?
+                }
+                (5, UnknownValue::Varint(nullability)) => {
+                    self.nullability = match nullability {
+                        1 => Some(Nullability::Nullable),
+                        2 => Some(Nullability::NonNull),
+                        _ => None,
+                    }
+                }
+                (6, UnknownValue::LengthDelimited(scalar)) => self.scalar = string(scalar)?,
+                (7, UnknownValue::Varint(operation)) => {
+                    self.operation = match operation {
+                        1 => Some(Operation::Query),
+                        2 => Some(Operation::Mutation),
+                        _ => None,
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::CodedOutputStream;
+
+    fn extension(options: &UnknownFields) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut output = CodedOutputStream::vec(&mut bytes);
+            output.write_unknown_fields(options).unwrap();
+            output.flush().unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn options_are_merged() {
+        let mut first = UnknownFields::new();
+        first.add_length_delimited(1, b"pie".to_vec());
+        first.add_varint(5, 2);
+        let mut second = UnknownFields::new();
+        second.add_length_delimited(1, b"pizza".to_vec());
+        second.add_varint(7, 1);
+        second.add_varint(99, 1);
+        let mut unknown_fields = UnknownFields::new();
+        unknown_fields.add_length_delimited(EXTENSION_NUMBER, extension(&first));
+        unknown_fields.add_length_delimited(EXTENSION_NUMBER, extension(&second));
+
+        assert_eq!(
+            GraphqlOptions::parse(&unknown_fields),
+            Ok(GraphqlOptions {
+                name: Some("pizza".to_string()),
+                nullability: Some(Nullability::NonNull),
+                operation: Some(Operation::Query),
+                ..GraphqlOptions::default()
+            })
+        );
+        assert_eq!(
+            GraphqlOptions::parse(&UnknownFields::new()),
+            Ok(GraphqlOptions::default())
+        );
+    }
+
+    #[test]
+    fn deprecation_reason_falls_back_to_the_comment() {
+        let options = GraphqlOptions::default();
+        assert_eq!(options.deprecation(false, " Old.\n"), None);
+        assert_eq!(
+            options.deprecation(true, " Use `crust`\n instead.\n"),
+            Some("Use `crust` instead.".to_string())
+        );
+        assert_eq!(options.deprecation(true, ""), Some(String::new()));
+
+        let options = GraphqlOptions {
+            deprecation_reason: Some("Gone".to_string()),
+            ..GraphqlOptions::default()
+        };
+        assert_eq!(
+            options.deprecation(false, " Old."),
+            Some("Gone".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_options_are_errors() {
+        let mut unknown_fields = UnknownFields::new();
+        unknown_fields.add_length_delimited(EXTENSION_NUMBER, vec![0x0a, 0x05, b'p']);
+
+        assert!(GraphqlOptions::parse(&unknown_fields).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+//! The comments and spans protoc records in `SourceCodeInfo`, found by the path of the element
+//! they belong to.
+//!
+//! A path lists the field numbers and the indices in repeated fields leading from the file
+//! descriptor to the element, see `SourceCodeInfo.Location.path` in descriptor.proto. Indices are
+//! the positions in the descriptors, not the field numbers.
+
+use config::Comments;
+use error::{Location, Span};
+use gql;
+use protobuf::descriptor::{FileDescriptorProto, SourceCodeInfo_Location};
+use std::collections::HashMap;
+
+// The numbers of the fields of the descriptors that paths go through.
+const FILE_MESSAGE_TYPE: i32 = 4;
+const FILE_ENUM_TYPE: i32 = 5;
+const FILE_SERVICE: i32 = 6;
+const MESSAGE_FIELD: i32 = 2;
+const MESSAGE_NESTED_TYPE: i32 = 3;
+const MESSAGE_ENUM_TYPE: i32 = 4;
+const MESSAGE_ONEOF_DECL: i32 = 8;
+const ENUM_VALUE: i32 = 2;
+const SERVICE_METHOD: i32 = 2;
+
+/// The path of a message, a field, a oneof, an enum, an enum value, a service or a method.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Path(Vec<i32>);
+
+impl Path {
+    /// A message declared at the top of the file.
+    pub fn message(index: usize) -> Path {
+        Path(vec![FILE_MESSAGE_TYPE, index as i32])
+    }
+
+    /// An enum declared at the top of the file.
+    pub fn enum_(index: usize) -> Path {
+        Path(vec![FILE_ENUM_TYPE, index as i32])
+    }
+
+    pub fn service(index: usize) -> Path {
+        Path(vec![FILE_SERVICE, index as i32])
+    }
+
+    /// A message nested in this message.
+    pub fn nested_message(&self, index: usize) -> Path {
+        self.child(MESSAGE_NESTED_TYPE, index)
+    }
+
+    /// An enum nested in this message.
+    pub fn nested_enum(&self, index: usize) -> Path {
+        self.child(MESSAGE_ENUM_TYPE, index)
+    }
+
+    /// A field of this message.
+    pub fn field(&self, index: usize) -> Path {
+        self.child(MESSAGE_FIELD, index)
+    }
+
+    /// A oneof of this message.
+    pub fn oneof(&self, index: usize) -> Path {
+        self.child(MESSAGE_ONEOF_DECL, index)
+    }
+
+    /// A value of this enum.
+    pub fn value(&self, index: usize) -> Path {
+        self.child(ENUM_VALUE, index)
+    }
+
+    /// A method of this service.
+    pub fn method(&self, index: usize) -> Path {
+        self.child(SERVICE_METHOD, index)
+    }
+
+    fn child(&self, field_number: i32, index: usize) -> Path {
+        let mut path = self.0.clone();
+        path.extend(&[field_number, index as i32]);
+        Path(path)
+    }
+}
+
+impl ::std::ops::Deref for Path {
+    type Target = [i32];
+
+    fn deref(&self) -> &[i32] {
+        &self.0
+    }
+}
+
+/// The locations of a file, indexed by path, so that finding the one of an element does not
+/// depend on the size of the file.
+pub struct SourceInfo<'a> {
+    file_name: &'a str,
+    locations: HashMap<&'a [i32], &'a SourceCodeInfo_Location>,
+}
+
+impl<'a> SourceInfo<'a> {
+    pub fn new(file: &'a FileDescriptorProto) -> SourceInfo<'a> {
+        let mut locations = HashMap::new();
+        for location in file.get_source_code_info().get_location() {
+            // protoc records the element itself first, then the parts of it.
+            locations.entry(location.get_path()).or_insert(location);
+        }
+
+        SourceInfo {
+            file_name: file.get_name(),
+            locations,
+        }
+    }
+
+    pub fn location(&self, path: &Path) -> Option<&'a SourceCodeInfo_Location> {
+        self.locations.get(&**path).cloned()
+    }
+
+    /// Where an error about the element at `path`, by its fully qualified name, originates.
+    pub fn locate(&self, element: &str, path: &Path) -> Location {
+        Location::new(
+            self.file_name,
+            element,
+            self.location(path)
+                .and_then(|location| Span::from_proto(location.get_span())),
+        )
+    }
+
+    /// The description made of the comments of an element the config keeps, empty when it has
+    /// none.
+    pub fn comments(&self, path: &Path, comments: &Comments) -> String {
+        self.location(path)
+            .map(|location| gql::from_comments(location, comments))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protobuf::RepeatedField;
+
+    fn commented(path: &Path, comment: &str) -> SourceCodeInfo_Location {
+        let mut location = SourceCodeInfo_Location::new();
+        location.set_path(path.to_vec());
+        location.set_leading_comments(comment.to_string());
+        location
+    }
+
+    #[test]
+    fn comments_are_found_by_index() {
+        let pizza = Path::message(1);
+        let crust = Path::message(1).nested_message(0);
+        let size = Path::message(1).nested_enum(0);
+        let mut file = FileDescriptorProto::new();
+        file.mut_source_code_info()
+            .set_location(RepeatedField::from_vec(vec![
+                commented(&Path::message(0), " Sauce."),
+                commented(&pizza, " Pizza."),
+                commented(&pizza.field(0), " Topping, numbered 7."),
+                commented(&pizza.field(1), " Price, numbered 2."),
+                commented(&crust.field(0), " Crust thickness."),
+                commented(&size.value(0), " Small."),
+                commented(&size.value(1), " Large."),
+                // The name of the value, after the value itself.
+                commented(&size.value(1).child(1, 0), " Not the value."),
+                commented(&Path::service(0).method(1), " Bake."),
+            ]));
+
+        let source_info = SourceInfo::new(&file);
+        let comments = Comments::default();
+        assert_eq!(
+            source_info.comments(&pizza.field(0), &comments),
+            "Topping, numbered 7."
+        );
+        assert_eq!(
+            source_info.comments(&pizza.field(1), &comments),
+            "Price, numbered 2."
+        );
+        assert_eq!(source_info.comments(&pizza.field(2), &comments), "");
+        assert_eq!(
+            source_info.comments(&crust.field(0), &comments),
+            "Crust thickness."
+        );
+        assert_eq!(source_info.comments(&size.value(0), &comments), "Small.");
+        assert_eq!(source_info.comments(&size.value(1), &comments), "Large.");
+        assert_eq!(
+            source_info.comments(&Path::service(0).method(1), &comments),
+            "Bake."
+        );
+    }
+
+    #[test]
+    fn errors_are_located_by_path() {
+        let field = Path::message(0).field(1);
+        let mut location = SourceCodeInfo_Location::new();
+        location.set_path(field.to_vec());
+        location.set_span(vec![11, 4, 24]);
+        let mut file = FileDescriptorProto::new();
+        file.set_name("pizza.proto".to_string());
+        file.mut_source_code_info()
+            .set_location(RepeatedField::from_vec(vec![location]));
+
+        let source_info = SourceInfo::new(&file);
+        assert_eq!(
+            source_info.locate("pizzeria.Pizza.extras", &field),
+            Location::new(
+                "pizza.proto",
+                "pizzeria.Pizza.extras",
+                Some(Span {
+                    start_line: 11,
+                    start_column: 4,
+                    end_line: 11,
+                    end_column: 24,
+                })
+            )
+        );
+        assert_eq!(
+            source_info.locate("pizzeria.Pizza", &Path::message(0)).span,
+            None
+        );
+    }
+}
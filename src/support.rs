@@ -1,17 +1,30 @@
-use regex::Regex;
+use protobuf::UnknownFields;
 
 pub fn strip_leading_dots(input: &str) -> &str {
-    let re = Regex::new(r"^\.+(.*)$").unwrap();
-    match re.captures(input) {
-        Some(cap) => cap.get(1).unwrap().as_str(),
-        None => input,
-    }
+    input.trim_start_matches('.')
+}
+
+/// The value of a varint field the protobuf version we build against does not know about, like
+/// the descriptor fields added after it. The last value wins, as for any scalar field.
+pub fn unknown_varint(unknown_fields: &UnknownFields, number: u32) -> Option<u64> {
+    unknown_fields
+        .get(number)
+        .and_then(|values| values.varint.last().cloned())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn unknown_varint_works() {
+        let mut unknown_fields = UnknownFields::new();
+        assert_eq!(unknown_varint(&unknown_fields, 17), None);
+        unknown_fields.add_varint(17, 0);
+        unknown_fields.add_varint(17, 1);
+        assert_eq!(unknown_varint(&unknown_fields, 17), Some(1));
+    }
+
     #[test]
     fn strip_leading_dots_works() {
         assert_eq!(strip_leading_dots("..abc"), "abc");